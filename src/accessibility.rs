@@ -0,0 +1,148 @@
+use crate::animation::{TextSizeLens, Tween};
+use crate::text_sprite::{TextSprite, TextSpriteSize};
+use crate::theme::to_color;
+use crate::{Dying, GameSquare, Grid, GridPosition, SquareColors, SQUARE_WIDTH};
+use bevy::prelude::*;
+use bevy_squares::render::Palette;
+use bevy_squares::rules::TileKind;
+
+const LABEL_FONT_SIZE: f32 = 28.0;
+
+/// Keeps the squares and texts in line with the `Accessibility` settings
+///
+/// Reduced motion is part of `AnimationSettings`, as every animation has to follow it.
+#[derive(Default)]
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Accessibility>()
+            .add_system(scale_texts.system())
+            .add_system(add_square_labels.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, update_square_labels.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, apply_palette.system());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Accessibility {
+    pub palette: Palette,
+    /// Shows the score on every square, so they can be told apart without their color
+    pub tile_numbers: bool,
+    /// Multiplies the size of every text in the interface
+    pub text_scale: f32,
+}
+
+impl Default for Accessibility {
+    fn default() -> Accessibility {
+        Accessibility {
+            palette: Palette::default(),
+            tile_numbers: false,
+            text_scale: 1.,
+        }
+    }
+}
+
+/// The font size a text was spawned with, before it was scaled
+struct BaseFontSize(f32);
+
+/// The entity drawing the score of a square, one of its children
+struct SquareLabel(Entity);
+
+fn scale_texts(
+    mut commands: Commands,
+    accessibility: Res<Accessibility>,
+    mut new_query: Query<Without<BaseFontSize, (Entity, &mut Text)>>,
+    // Texts that are growing or shrinking are scaled again once they are done
+    mut text_query: Query<Without<Tween<TextSizeLens>, (&BaseFontSize, &mut Text)>>,
+) {
+    for (entity, mut text) in &mut new_query.iter() {
+        let base = text.style.font_size;
+        commands.insert_one(entity, BaseFontSize(base));
+        text.style.font_size = base * accessibility.text_scale;
+    }
+
+    for (base, mut text) in &mut text_query.iter() {
+        let font_size = base.0 * accessibility.text_scale;
+        // Only touch the text when it changes, as every change lays out the glyphs again
+        if text.style.font_size != font_size {
+            text.style.font_size = font_size;
+        }
+    }
+}
+
+/// Gives every new square a label, which stays empty unless tile numbers are shown
+fn add_square_labels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut square_query: Query<Without<SquareLabel, With<GameSquare, Entity>>>,
+) {
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+    let center = Vec3::new(SQUARE_WIDTH as f32 / 2.0, SQUARE_WIDTH as f32 / 2.0, 0.2);
+
+    for square in &mut square_query.iter() {
+        commands.spawn((
+            TextSprite {
+                text: String::new(),
+                text_style: TextStyle {
+                    font_size: LABEL_FONT_SIZE,
+                    color: Color::NONE,
+                },
+                font: font_handle.clone(),
+            },
+            TextSpriteSize::default(),
+            Translation(center),
+            Transform::default(),
+            LocalTransform::default(),
+            Draw {
+                is_transparent: true,
+                ..Default::default()
+            },
+        ));
+        let label = commands.current_entity().unwrap();
+        commands.push_children(square, &[label]);
+        commands.insert_one(square, SquareLabel(label));
+    }
+}
+
+fn update_square_labels(
+    accessibility: Res<Accessibility>,
+    grid: Res<Grid>,
+    mut square_query: Query<Without<Dying, With<GameSquare, (&GridPosition, &SquareLabel)>>>,
+    label_query: Query<&mut TextSprite>,
+) {
+    for (position, label) in &mut square_query.iter() {
+        let tile = match grid.get_at((position.0, position.1)) {
+            Some(square) => square.tile,
+            None => continue,
+        };
+        let text = match tile.kind {
+            TileKind::Wall => String::new(),
+            _ if accessibility.tile_numbers => tile.to_string(),
+            _ => String::new(),
+        };
+        let color = to_color(accessibility.palette.label_color(tile));
+
+        if let Ok(mut sprite) = label_query.get_mut::<TextSprite>(label.0) {
+            // Changed text sprites lay out their glyphs again
+            if sprite.text != text {
+                sprite.text = text;
+            }
+            if sprite.text_style.color != color {
+                sprite.text_style.color = color;
+            }
+        }
+    }
+}
+
+fn apply_palette(
+    accessibility: Res<Accessibility>,
+    mut colors: ResMut<SquareColors>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if colors.palette != accessibility.palette {
+        colors.set_palette(accessibility.palette, &mut materials);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::animation::{AnimationKind, AnimationSettings};
+use crate::hint;
+use crate::rules::{Board, MovementDirection, Spawner};
+use crate::{GameSession, GameState, Grid, MoveRequest, RunningGameState};
+use bevy::prelude::*;
+use std::str::FromStr;
+
+/// Seconds between two moves of the computer at the normal animation speed, so that they can
+/// still be followed
+const AI_MOVE_INTERVAL: f32 = 0.25;
+/// How many moves expectimax looks ahead, each followed by every possible spawn
+const EXPECTIMAX_DEPTH: u32 = 2;
+/// How much a free cell at the end of the lookahead is worth compared to points
+const EMPTY_CELL_WEIGHT: f32 = 4.0;
+
+/// Lets the computer play, chosen with `--ai`
+#[derive(Default)]
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AutoPlayer>()
+            .add_system_to_stage(bevy::app::stage::PRE_UPDATE, play_automatically.system());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Strategy {
+    /// The lookahead of the hints, which ignores new squares
+    Greedy,
+    /// Also weighs every square that may spawn after a move by its chance
+    Expectimax,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match s {
+            "greedy" => Ok(Strategy::Greedy),
+            "expectimax" => Ok(Strategy::Expectimax),
+            _ => Err(format!(
+                "unknown strategy `{}`, expected greedy or expectimax",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AutoPlayer {
+    pub strategy: Option<Strategy>,
+    until_next_move: f32,
+}
+
+impl AutoPlayer {
+    pub fn new(strategy: Option<Strategy>) -> AutoPlayer {
+        AutoPlayer {
+            strategy,
+            until_next_move: AI_MOVE_INTERVAL,
+        }
+    }
+}
+
+/// The move the strategy would make, if any move is possible
+pub fn choose_move(
+    strategy: Strategy,
+    board: &Board,
+    spawner: &Spawner,
+) -> Option<MovementDirection> {
+    match strategy {
+        Strategy::Greedy => hint::best_direction(board),
+        Strategy::Expectimax => MovementDirection::ALL
+            .iter()
+            .filter_map(|&direction| {
+                expected_value(board, spawner, direction, EXPECTIMAX_DEPTH)
+                    .map(|value| (direction, value))
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(direction, _)| direction),
+    }
+}
+
+/// The points of the move plus the average value of the boards it may lead to
+fn expected_value(
+    board: &Board,
+    spawner: &Spawner,
+    direction: MovementDirection,
+    depth: u32,
+) -> Option<f32> {
+    let mut after = board.clone();
+    let turn = after.slide(direction);
+    if !turn.moved() {
+        return None;
+    }
+
+    let spawns = spawner.possible_spawns(board, &after, direction);
+    let future = if spawns.is_empty() {
+        best_value(&after, spawner, depth - 1)
+    } else {
+        let total: f32 = spawns
+            .iter()
+            .map(|spawn| {
+                let mut next = after.clone();
+                next.set_tile(spawn.position, Some(spawn.tile));
+                best_value(&next, spawner, depth - 1)
+            })
+            .sum();
+        total / spawns.len() as f32
+    };
+
+    Some(turn.points() as f32 + future)
+}
+
+/// The value of the best move on the board, or of the board itself at the end of the lookahead
+fn best_value(board: &Board, spawner: &Spawner, depth: u32) -> f32 {
+    let free_cells = (board.size() * board.size()) as usize - board.tiles().count();
+    if depth == 0 {
+        return free_cells as f32 * EMPTY_CELL_WEIGHT;
+    }
+
+    // A board without moves ends the game, so nothing more can be gained
+    MovementDirection::ALL
+        .iter()
+        .filter_map(|&direction| expected_value(board, spawner, direction, depth))
+        .fold(0., f32::max)
+}
+
+fn play_automatically(
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    game_state: Res<GameState>,
+    grid: Res<Grid>,
+    mut session: ResMut<GameSession>,
+    mut auto_player: ResMut<AutoPlayer>,
+    mut move_requests: ResMut<Events<MoveRequest>>,
+) {
+    let strategy = match auto_player.strategy {
+        Some(strategy) => strategy,
+        None => return,
+    };
+
+    if game_state.state != RunningGameState::Running || session.replay.is_some() {
+        return;
+    }
+
+    auto_player.until_next_move -=
+        settings.delta_seconds(AnimationKind::Sequence, time.delta_seconds);
+    if auto_player.until_next_move > 0. {
+        return;
+    }
+    auto_player.until_next_move = AI_MOVE_INTERVAL;
+
+    if let Some(direction) = choose_move(strategy, &grid.board(), &session.spawner) {
+        // The computer playing counts like taking a hint for every move
+        session.stats.hints += 1;
+        move_requests.send(MoveRequest(direction));
+    }
+}
@@ -1,143 +1,857 @@
 use bevy::prelude::*;
+use std::f32::consts::PI;
+use std::marker::PhantomData;
 
 #[derive(Default)]
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(update_move_to.system());
-        app.add_system(update_scale_to.system());
-        app.add_system(update_chase_number.system());
+        app.init_resource::<AnimationSettings>()
+            .add_event::<AnimationFinished>()
+            .add_system(update_material_color_tween.system())
+            .add_system(update_sequences.system());
+
+        add_tween_systems::<TranslationLens>(app);
+        add_tween_systems::<ScaleLens>(app);
+        add_tween_systems::<RotationLens>(app);
+        add_tween_systems::<UiPositionLens>(app);
+        add_tween_systems::<TextSizeLens>(app);
+        add_tween_systems::<TextNumberLens>(app);
     }
 }
 
-#[derive(Default)]
-pub struct ChaseNumber {
-    pub elapsed_time: f32,
-    pub duration: f32,
-    pub delay: f32,
-    pub start_number: f32,
-    pub end_number: f32,
-    pub cur_number: f32,
-    pub ease: Easing,
+/// Registers the systems playing tweens through `L`, for lenses defined outside this module
+pub fn add_tween_systems<L: ComponentLens>(app: &mut AppBuilder) {
+    app.add_system(start_pending_tweens::<L>.system())
+        .add_system(update_tween::<L>.system());
 }
 
-fn update_chase_number(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut chase_number_query: Query<(Entity, &mut ChaseNumber)>,
-) {
-    for (entity, mut chase_number) in &mut chase_number_query.iter() {
-        chase_number.elapsed_time += time.delta_seconds;
-        let done = (chase_number.elapsed_time / chase_number.duration).min(1.0);
+pub struct AnimationSettings {
+    /// How fast animations play, `2.0` is twice as fast and `0.0` skips them entirely
+    pub time_scale: f32,
+    /// Skips everything that moves, scales or bounces, while fades still play
+    pub reduced_motion: bool,
+}
+
+impl AnimationSettings {
+    /// How far animations of `kind` advance in a frame which took `delta_seconds`
+    pub fn delta_seconds(&self, kind: AnimationKind, delta_seconds: f32) -> f32 {
+        if self.time_scale.is_nan()
+            || self.time_scale <= 0.
+            || (self.reduced_motion && kind.is_motion())
+        {
+            std::f32::INFINITY
+        } else {
+            delta_seconds * self.time_scale
+        }
+    }
+}
+
+impl Default for AnimationSettings {
+    fn default() -> AnimationSettings {
+        AnimationSettings {
+            time_scale: 1.,
+            reduced_motion: false,
+        }
+    }
+}
+
+/// Values which can be interpolated by a [`Tween`]
+pub trait Lerp: Clone + Send + Sync + 'static {
+    /// Interpolates between `self` at `t = 0` and `other` at `t = 1`, `t` may overshoot
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(&self, other: &Vec2, t: f32) -> Vec2 {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, other: &Vec3, t: f32) -> Vec3 {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Lerp for Quat {
+    fn lerp(&self, other: &Quat, t: f32) -> Quat {
+        Quat::lerp(*self, *other, t)
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(&self, other: &Color, t: f32) -> Color {
+        Color::rgba(
+            self.r.lerp(&other.r, t),
+            self.g.lerp(&other.g, t),
+            self.b.lerp(&other.b, t),
+            self.a.lerp(&other.a, t),
+        )
+    }
+}
+
+/// Selects which property of an entity a [`Tween`] animates
+pub trait Lens: Send + Sync + 'static {
+    type Value: Lerp;
+
+    const KIND: AnimationKind;
+}
+
+/// A lens onto a property stored directly in a component
+pub trait ComponentLens: Lens {
+    type Target: Component;
+
+    fn set(target: &mut Self::Target, value: Self::Value);
+}
+
+pub struct TranslationLens;
+
+impl Lens for TranslationLens {
+    type Value = Vec3;
+
+    const KIND: AnimationKind = AnimationKind::Translation;
+}
+
+impl ComponentLens for TranslationLens {
+    type Target = Translation;
+
+    fn set(target: &mut Translation, value: Vec3) {
+        target.0 = value;
+    }
+}
+
+pub struct ScaleLens;
+
+impl Lens for ScaleLens {
+    type Value = f32;
+
+    const KIND: AnimationKind = AnimationKind::Scale;
+}
+
+impl ComponentLens for ScaleLens {
+    type Target = Scale;
+
+    fn set(target: &mut Scale, value: f32) {
+        target.0 = value;
+    }
+}
+
+pub struct RotationLens;
+
+impl Lens for RotationLens {
+    type Value = Quat;
+
+    const KIND: AnimationKind = AnimationKind::Rotation;
+}
+
+impl ComponentLens for RotationLens {
+    type Target = Rotation;
+
+    fn set(target: &mut Rotation, value: Quat) {
+        target.0 = value;
+    }
+}
+
+/// Moves a UI node by its `left` and `top` position in pixels
+pub struct UiPositionLens;
+
+impl Lens for UiPositionLens {
+    type Value = Vec2;
+
+    const KIND: AnimationKind = AnimationKind::UiPosition;
+}
+
+impl ComponentLens for UiPositionLens {
+    type Target = Style;
+
+    fn set(target: &mut Style, value: Vec2) {
+        target.position.left = Val::Px(value.x());
+        target.position.top = Val::Px(value.y());
+    }
+}
+
+pub struct TextSizeLens;
+
+impl Lens for TextSizeLens {
+    type Value = f32;
+
+    const KIND: AnimationKind = AnimationKind::TextSize;
+}
+
+impl ComponentLens for TextSizeLens {
+    type Target = Text;
+
+    fn set(target: &mut Text, value: f32) {
+        target.style.font_size = value;
+    }
+}
+
+/// Counts the text up or down as a whole number
+pub struct TextNumberLens;
+
+impl Lens for TextNumberLens {
+    type Value = f32;
+
+    const KIND: AnimationKind = AnimationKind::TextNumber;
+}
+
+impl ComponentLens for TextNumberLens {
+    type Target = Text;
 
-        let ease: &dyn Fn(f32) -> f32 = chase_number.ease.get_ease_fn();
+    fn set(target: &mut Text, value: f32) {
+        target.value = (value as u64).to_string();
+    }
+}
+
+/// Changes the color of the entity's material
+///
+/// The material asset itself is changed, so entities sharing it change as well.
+pub struct MaterialColorLens;
 
-        chase_number.cur_number = chase_number.start_number
-            + (chase_number.end_number - chase_number.start_number) * ease(done);
+impl Lens for MaterialColorLens {
+    type Value = Color;
 
-        if chase_number.elapsed_time > (chase_number.duration + chase_number.delay) {
-            chase_number.cur_number = chase_number.end_number;
-            commands.remove_one::<ChaseNumber>(entity);
+    const KIND: AnimationKind = AnimationKind::MaterialColor;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AnimationKind {
+    Translation,
+    Scale,
+    Rotation,
+    UiPosition,
+    TextSize,
+    TextNumber,
+    MaterialColor,
+    TextSpriteColor,
+    Sequence,
+}
+
+impl AnimationKind {
+    /// Whether the animation moves or resizes something, instead of changing its color or value
+    pub fn is_motion(self) -> bool {
+        match self {
+            AnimationKind::Translation
+            | AnimationKind::Scale
+            | AnimationKind::Rotation
+            | AnimationKind::UiPosition
+            | AnimationKind::TextSize => true,
+            AnimationKind::TextNumber
+            | AnimationKind::MaterialColor
+            | AnimationKind::TextSpriteColor
+            | AnimationKind::Sequence => false,
         }
     }
 }
 
-#[derive(Default)]
-pub struct ScaleTo {
-    pub elapsed_time: f32,
+/// Sent once a tween or sequence has played to the end, after its [`OnFinish`] action
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub kind: AnimationKind,
+}
+
+/// What happens to the entity once an animation has finished
+///
+/// The animation itself is always removed.
+pub enum OnFinish {
+    Remove,
+    Despawn,
+    /// Continues with another animation on the same entity
+    Then(Box<Sequence>),
+}
+
+impl Default for OnFinish {
+    fn default() -> OnFinish {
+        OnFinish::Remove
+    }
+}
+
+impl OnFinish {
+    /// Whether the entity is despawned once everything this continues with has played
+    fn despawns(&self) -> bool {
+        match self {
+            OnFinish::Remove => false,
+            OnFinish::Despawn => true,
+            OnFinish::Then(next) => next.on_finish.despawns(),
+        }
+    }
+
+    /// Does `then` once everything this continues with has played, unless the entity is gone
+    fn and_then(self, then: OnFinish) -> OnFinish {
+        match self {
+            OnFinish::Remove => then,
+            OnFinish::Despawn => OnFinish::Despawn,
+            OnFinish::Then(mut next) => {
+                next.on_finish = std::mem::take(&mut next.on_finish).and_then(then);
+                OnFinish::Then(next)
+            }
+        }
+    }
+}
+
+/// Removes the finished animation `T`, runs `on_finish` and reports it
+fn finish<T: Component>(
+    commands: &mut Commands,
+    finished_events: &mut Events<AnimationFinished>,
+    entity: Entity,
+    kind: AnimationKind,
+    on_finish: OnFinish,
+) {
+    commands.remove_one::<T>(entity);
+
+    match on_finish {
+        OnFinish::Remove => {}
+        OnFinish::Despawn => {
+            commands.despawn_recursive(entity);
+        }
+        OnFinish::Then(next) => {
+            commands.insert_one(entity, *next);
+        }
+    }
+
+    finished_events.send(AnimationFinished { entity, kind });
+}
+
+/// How a tween played with [`TweenCommands::play`] takes over from one already running
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interrupt {
+    /// Stops the running tween and starts right away
+    Replace,
+    /// Fades from the running tween into this one over the given seconds, which keeps the
+    /// motion smooth as both the value and its speed carry over
+    Blend(f32),
+    /// Plays once the running tween and everything it continues with have finished
+    Queue,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Repeat {
+    Never,
+    /// Plays the tween this many more times after the first
+    Times(u32),
+    Forever,
+}
+
+/// Animates the property selected by `L` from `start` to `end`
+///
+/// The tween waits for `delay` seconds, then plays for `duration` seconds and as often again as
+/// `repeat` says. With `ping_pong` every other repetition plays backwards. Once done the final
+/// value is set and `on_finish` decides what happens next.
+pub struct Tween<L: Lens> {
+    pub start: L::Value,
+    pub end: L::Value,
     pub duration: f32,
-    pub looping: bool,
-    pub start_scale: Scale,
-    pub end_scale: Scale,
+    pub delay: f32,
+    pub repeat: Repeat,
+    pub ping_pong: bool,
     pub ease: Easing,
+    pub on_finish: OnFinish,
+    pub interrupt: Interrupt,
+    elapsed_time: f32,
+    blend: Option<Blend<L>>,
+    lens: PhantomData<L>,
 }
 
-fn update_scale_to(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut move_to_query: Query<(Entity, &mut ScaleTo, &mut Scale)>,
-) {
-    for (entity, mut move_to, mut scale) in &mut move_to_query.iter() {
-        move_to.elapsed_time += time.delta_seconds;
+/// The tween that was interrupted, which keeps playing until the blend is done
+struct Blend<L: Lens> {
+    previous: Box<Tween<L>>,
+    duration: f32,
+}
 
-        let done = (move_to.elapsed_time / move_to.duration).min(1.0);
+impl<L: Lens> Tween<L> {
+    pub fn new(start: L::Value, end: L::Value, duration: f32) -> Tween<L> {
+        Tween {
+            start,
+            end,
+            duration,
+            delay: 0.,
+            repeat: Repeat::Never,
+            ping_pong: false,
+            ease: Easing::default(),
+            on_finish: OnFinish::Remove,
+            interrupt: Interrupt::Replace,
+            elapsed_time: 0.,
+            blend: None,
+            lens: PhantomData,
+        }
+    }
+
+    pub fn with_ease(mut self, ease: Easing) -> Tween<L> {
+        self.ease = ease;
+        self
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Tween<L> {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Tween<L> {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_ping_pong(mut self) -> Tween<L> {
+        self.ping_pong = true;
+        self
+    }
+
+    pub fn on_finish(mut self, on_finish: OnFinish) -> Tween<L> {
+        self.on_finish = on_finish;
+        self
+    }
+
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Tween<L> {
+        self.interrupt = interrupt;
+        self
+    }
+
+    /// Plays `next` once this tween and everything it continues with have finished
+    ///
+    /// Gives `next` back if the entity gets despawned in the end, as there is nothing to play it
+    /// on.
+    fn queue(&mut self, next: Tween<L>) -> Result<(), Tween<L>> {
+        if self.on_finish.despawns() {
+            return Err(next);
+        }
+        let on_finish = std::mem::take(&mut self.on_finish);
+        self.on_finish = on_finish.and_then(OnFinish::Then(Box::new(next.into())));
+        Ok(())
+    }
+
+    /// Fades from this tween into `next` over `duration` seconds
+    ///
+    /// What this tween would have done once finished still happens, after `next` has finished.
+    fn blend_into(&mut self, mut next: Tween<L>, duration: f32) {
+        std::mem::swap(self, &mut next);
+        let on_finish = std::mem::take(&mut self.on_finish);
+        self.on_finish = on_finish.and_then(std::mem::take(&mut next.on_finish));
+        self.blend = Some(Blend {
+            previous: Box::new(next),
+            duration,
+        });
+    }
+
+    /// How long the tween plays including its delay, infinite if it repeats forever
+    pub fn total_duration(&self) -> f32 {
+        let passes = match self.repeat {
+            Repeat::Never => 1.,
+            Repeat::Times(times) => times as f32 + 1.,
+            Repeat::Forever => return std::f32::INFINITY,
+        };
+
+        self.delay + self.duration * passes
+    }
 
-        let ease: &dyn Fn(f32) -> f32 = move_to.ease.get_ease_fn();
+    /// Advances the tween, returning the current value and whether it has finished
+    ///
+    /// Advancing by an infinite time ends even tweens which repeat forever.
+    fn tick(&mut self, delta: f32) -> (L::Value, bool) {
+        self.elapsed_time += delta;
 
-        scale.0 =
-            move_to.start_scale.0 + (move_to.end_scale.0 - move_to.start_scale.0) * ease(done);
+        let time = (self.elapsed_time - self.delay).max(0.);
+        let pass = (time / self.duration) as u64;
+        let last_pass = match self.repeat {
+            Repeat::Never => 0,
+            Repeat::Times(times) => times as u64,
+            Repeat::Forever => u64::MAX,
+        };
 
-        if move_to.elapsed_time > move_to.duration {
-            if move_to.looping {
-                move_to.elapsed_time %= move_to.duration;
+        let (pass, progress, finished) =
+            if self.duration <= 0. || !time.is_finite() || pass > last_pass {
+                (last_pass, 1., true)
             } else {
-                *scale = move_to.end_scale;
-                commands.remove_one::<ScaleTo>(entity);
+                (pass, (time % self.duration) / self.duration, false)
+            };
+
+        let (from, to) = if self.ping_pong && pass % 2 == 1 {
+            (&self.end, &self.start)
+        } else {
+            (&self.start, &self.end)
+        };
+        if finished {
+            return (to.clone(), true);
+        }
+
+        let value = from.lerp(to, self.ease.ease(progress));
+        let value = match self.blend.as_mut() {
+            Some(blend) => {
+                let (previous, _) = blend.previous.tick(delta);
+                let weight = (self.elapsed_time / blend.duration).min(1.);
+                // Eases in and out, so the speed of the previous tween carries over
+                previous.lerp(&value, Easing::EaseInOutCubic.ease(weight))
             }
+            None => value,
+        };
+
+        if self
+            .blend
+            .as_ref()
+            .map_or(false, |blend| self.elapsed_time >= blend.duration)
+        {
+            self.blend = None;
         }
+
+        (value, false)
     }
 }
 
+/// Runs a step of a [`Sequence`], with how late it started in seconds
+type Action = Box<dyn FnOnce(&mut Commands, Entity, f32) + Send + Sync>;
+
+struct Step {
+    start: f32,
+    action: Option<Action>,
+}
+
+/// Plays tweens and callbacks on its entity one after another or together
+///
+/// ```ignore
+/// Sequence::new()
+///     .wait(0.1)
+///     .then(Tween::<ScaleLens>::new(1., 1.2, 0.1))
+///     .then(Tween::<TranslationLens>::new(from, to, 0.3))
+///     .together(Tween::<ScaleLens>::new(1.2, 0., 0.3))
+///     .on_finish(OnFinish::Despawn)
+/// ```
+///
+/// Sequences nest, so a group of tweens built as its own sequence can be played with `then` or
+/// `together` as one step.
 #[derive(Default)]
-pub struct MoveTo {
-    pub elapsed_time: f32,
-    pub duration: f32,
-    pub loop_count: u32,
-    pub start_position: Translation,
-    pub end_position: Translation,
-    pub ease: Easing,
-    pub bounce: bool,
+pub struct Sequence {
+    steps: Vec<Step>,
+    last_start: f32,
+    duration: f32,
+    elapsed_time: f32,
+    on_finish: OnFinish,
 }
 
-fn update_move_to(
+impl Sequence {
+    pub fn new() -> Sequence {
+        Default::default()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Plays `animation` once everything added so far has finished
+    pub fn then(self, animation: impl Into<Sequence>) -> Sequence {
+        let start = self.duration;
+        self.add(start, animation.into())
+    }
+
+    /// Plays `animation` at the same time as the previously added step
+    pub fn together(self, animation: impl Into<Sequence>) -> Sequence {
+        let start = self.last_start;
+        self.add(start, animation.into())
+    }
+
+    /// Waits before playing the next step added with `then`
+    pub fn wait(mut self, seconds: f32) -> Sequence {
+        self.last_start = self.duration;
+        self.duration += seconds;
+        self
+    }
+
+    /// Calls `callback` once everything added so far has finished
+    pub fn callback(
+        self,
+        callback: impl FnOnce(&mut Commands, Entity) + Send + Sync + 'static,
+    ) -> Sequence {
+        self.then(Sequence::from_action(
+            0.,
+            Box::new(move |commands, entity, _| callback(commands, entity)),
+        ))
+    }
+
+    /// What happens once every step has played
+    ///
+    /// A sequence played as a step of another one only sends [`AnimationFinished`] for the outer
+    /// sequence, but still despawns or continues once its own steps are done.
+    pub fn on_finish(mut self, on_finish: OnFinish) -> Sequence {
+        self.on_finish = on_finish;
+        self
+    }
+
+    fn from_action(duration: f32, action: Action) -> Sequence {
+        Sequence {
+            steps: vec![Step {
+                start: 0.,
+                action: Some(action),
+            }],
+            duration,
+            ..Default::default()
+        }
+    }
+
+    fn add(mut self, start: f32, other: Sequence) -> Sequence {
+        self.steps.extend(other.steps.into_iter().map(|step| Step {
+            start: start + step.start,
+            action: step.action,
+        }));
+        self.duration = self.duration.max(start + other.duration);
+
+        let end = start + other.duration;
+        self = match other.on_finish {
+            OnFinish::Remove => self,
+            OnFinish::Despawn => self.add(
+                end,
+                Sequence::from_action(
+                    0.,
+                    Box::new(|commands, entity, _| {
+                        commands.despawn_recursive(entity);
+                    }),
+                ),
+            ),
+            OnFinish::Then(next) => self.add(end, *next),
+        };
+
+        self.last_start = start;
+        self
+    }
+}
+
+impl<L: Lens> From<Tween<L>> for Sequence {
+    fn from(mut tween: Tween<L>) -> Sequence {
+        Sequence::from_action(
+            tween.total_duration(),
+            Box::new(move |commands, entity, late| {
+                // Catch up on the time the step started late, so that steps stay in sync
+                tween.elapsed_time = late;
+                commands.insert_one(entity, tween);
+            }),
+        )
+    }
+}
+
+fn update_sequences(
     mut commands: Commands,
     time: Res<Time>,
-    mut move_to_query: Query<(Entity, &mut MoveTo, &mut Translation)>,
+    settings: Res<AnimationSettings>,
+    mut finished_events: ResMut<Events<AnimationFinished>>,
+    mut sequence_query: Query<(Entity, &mut Sequence)>,
 ) {
-    for (entity, mut move_to, mut translation) in &mut move_to_query.iter() {
-        move_to.elapsed_time += time.delta_seconds;
+    for (entity, mut sequence) in &mut sequence_query.iter() {
+        sequence.elapsed_time +=
+            settings.delta_seconds(AnimationKind::Sequence, time.delta_seconds);
+        let elapsed_time = sequence.elapsed_time;
 
-        let done = (move_to.elapsed_time / move_to.duration).min(1.0);
+        for step in sequence.steps.iter_mut() {
+            if step.start <= elapsed_time {
+                if let Some(action) = step.action.take() {
+                    action(&mut commands, entity, elapsed_time - step.start);
+                }
+            }
+        }
 
-        let ease: &dyn Fn(f32) -> f32 = move_to.ease.get_ease_fn();
+        if elapsed_time >= sequence.duration {
+            let on_finish = std::mem::take(&mut sequence.on_finish);
+            finish::<Sequence>(
+                &mut commands,
+                &mut finished_events,
+                entity,
+                AnimationKind::Sequence,
+                on_finish,
+            );
+        }
+    }
+}
 
-        translation.0 = move_to.start_position.0
-            + (move_to.end_position.0 - move_to.start_position.0) * ease(done);
+/// A tween waiting to take over from the one running on its entity
+struct PendingTween<L: Lens>(Option<Tween<L>>);
 
-        if move_to.elapsed_time > move_to.duration {
-            if move_to.loop_count > 0 {
-                move_to.loop_count -= 1;
-                move_to.elapsed_time %= move_to.duration;
-            } else {
-                *translation = move_to.end_position;
-                commands.remove_one::<MoveTo>(entity);
+pub trait TweenCommands {
+    /// Plays `tween` on `entity`, taking over from a running tween as its [`Interrupt`] says
+    fn play<L: ComponentLens>(&mut self, entity: Entity, tween: Tween<L>) -> &mut Self;
+}
+
+impl TweenCommands for Commands {
+    fn play<L: ComponentLens>(&mut self, entity: Entity, tween: Tween<L>) -> &mut Self {
+        match tween.interrupt {
+            Interrupt::Replace => self.insert_one(entity, tween),
+            Interrupt::Blend(_) | Interrupt::Queue => {
+                self.insert_one(entity, PendingTween(Some(tween)))
             }
+        }
+    }
+}
 
-            if move_to.bounce {
-                let end = move_to.end_position;
-                move_to.end_position = move_to.start_position;
-                move_to.start_position = end;
+fn start_pending_tweens<L: ComponentLens>(
+    mut commands: Commands,
+    mut pending_query: Query<(Entity, &mut PendingTween<L>, Option<&mut Tween<L>>)>,
+) {
+    for (entity, mut pending, running) in &mut pending_query.iter() {
+        commands.remove_one::<PendingTween<L>>(entity);
+
+        let tween = match pending.0.take() {
+            Some(tween) => tween,
+            None => continue,
+        };
+
+        match (running, tween.interrupt) {
+            (Some(mut running), Interrupt::Blend(duration)) => {
+                running.blend_into(tween, duration);
+            }
+            (Some(mut running), Interrupt::Queue) => {
+                if running.queue(tween).is_err() {
+                    eprintln!(
+                        "Dropped a tween queued on {:?}, which despawns before it could play",
+                        entity
+                    );
+                }
+            }
+            _ => {
+                commands.insert_one(entity, tween);
             }
         }
     }
 }
 
+fn update_tween<L: ComponentLens>(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut finished_events: ResMut<Events<AnimationFinished>>,
+    mut tween_query: Query<(Entity, &mut Tween<L>, &mut L::Target)>,
+) {
+    for (entity, mut tween, mut target) in &mut tween_query.iter() {
+        let (value, finished) = tween.tick(settings.delta_seconds(L::KIND, time.delta_seconds));
+        L::set(&mut target, value);
+
+        if finished {
+            let on_finish = std::mem::take(&mut tween.on_finish);
+            finish::<Tween<L>>(
+                &mut commands,
+                &mut finished_events,
+                entity,
+                L::KIND,
+                on_finish,
+            );
+        }
+    }
+}
+
+fn update_material_color_tween(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut finished_events: ResMut<Events<AnimationFinished>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut tween_query: Query<(
+        Entity,
+        &mut Tween<MaterialColorLens>,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    for (entity, mut tween, handle) in &mut tween_query.iter() {
+        let (value, finished) =
+            tween.tick(settings.delta_seconds(MaterialColorLens::KIND, time.delta_seconds));
+        if let Some(material) = materials.get_mut(&handle) {
+            material.color = value;
+        }
+
+        if finished {
+            let on_finish = std::mem::take(&mut tween.on_finish);
+            finish::<Tween<MaterialColorLens>>(
+                &mut commands,
+                &mut finished_events,
+                entity,
+                MaterialColorLens::KIND,
+                on_finish,
+            );
+        }
+    }
+}
+
+/// Easing curves, see <https://easings.net> for how they look
+///
+/// Every curve maps `0.0` to `0.0` and `1.0` to `1.0`, but some of them overshoot in between.
 #[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Easing {
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+    EaseInQuart,
+    EaseOutQuart,
+    EaseInOutQuart,
+    EaseInQuint,
+    EaseOutQuint,
+    EaseInOutQuint,
+    EaseInSine,
+    EaseOutSine,
+    EaseInOutSine,
+    EaseInExpo,
+    EaseOutExpo,
+    EaseInOutExpo,
     EaseInCirc,
+    EaseOutCirc,
     EaseInOutCirc,
+    EaseInBack,
     EaseOutBack,
+    EaseInOutBack,
+    EaseInElastic,
+    EaseOutElastic,
+    EaseInOutElastic,
+    EaseInBounce,
     EaseOutBounce,
+    EaseInOutBounce,
+    /// A CSS style `cubic-bezier(x1, y1, x2, y2)`, the x coordinates have to be within `0..=1`
+    CubicBezier(f32, f32, f32, f32),
+    /// Jumps in the given number of equal steps, at the end of each step
+    Steps(u32),
 }
 
 impl Easing {
-    fn get_ease_fn(&self) -> &dyn Fn(f32) -> f32 {
-        match self {
-            Easing::EaseInCirc => &ease_in_circ,
-            Easing::EaseInOutCirc => &ease_in_out_circ,
-            Easing::EaseOutBack => &ease_out_back,
-            Easing::EaseOutBounce => &ease_out_bounce,
+    pub fn ease(&self, x: f32) -> f32 {
+        match *self {
+            Easing::Linear => x,
+            Easing::EaseInQuad => ease_in_pow(x, 2),
+            Easing::EaseOutQuad => ease_out_pow(x, 2),
+            Easing::EaseInOutQuad => ease_in_out_pow(x, 2),
+            Easing::EaseInCubic => ease_in_pow(x, 3),
+            Easing::EaseOutCubic => ease_out_pow(x, 3),
+            Easing::EaseInOutCubic => ease_in_out_pow(x, 3),
+            Easing::EaseInQuart => ease_in_pow(x, 4),
+            Easing::EaseOutQuart => ease_out_pow(x, 4),
+            Easing::EaseInOutQuart => ease_in_out_pow(x, 4),
+            Easing::EaseInQuint => ease_in_pow(x, 5),
+            Easing::EaseOutQuint => ease_out_pow(x, 5),
+            Easing::EaseInOutQuint => ease_in_out_pow(x, 5),
+            Easing::EaseInSine => ease_in_sine(x),
+            Easing::EaseOutSine => ease_out_sine(x),
+            Easing::EaseInOutSine => ease_in_out_sine(x),
+            Easing::EaseInExpo => ease_in_expo(x),
+            Easing::EaseOutExpo => ease_out_expo(x),
+            Easing::EaseInOutExpo => ease_in_out_expo(x),
+            Easing::EaseInCirc => ease_in_circ(x),
+            Easing::EaseOutCirc => ease_out_circ(x),
+            Easing::EaseInOutCirc => ease_in_out_circ(x),
+            Easing::EaseInBack => ease_in_back(x),
+            Easing::EaseOutBack => ease_out_back(x),
+            Easing::EaseInOutBack => ease_in_out_back(x),
+            Easing::EaseInElastic => ease_in_elastic(x),
+            Easing::EaseOutElastic => ease_out_elastic(x),
+            Easing::EaseInOutElastic => ease_in_out_elastic(x),
+            Easing::EaseInBounce => ease_in_bounce(x),
+            Easing::EaseOutBounce => ease_out_bounce(x),
+            Easing::EaseInOutBounce => ease_in_out_bounce(x),
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x, x1, y1, x2, y2),
+            Easing::Steps(steps) => ease_steps(x, steps),
         }
     }
 }
@@ -148,6 +862,133 @@ impl Default for Easing {
     }
 }
 
+fn ease_in_pow(x: f32, power: i32) -> f32 {
+    x.powi(power)
+}
+
+fn ease_out_pow(x: f32, power: i32) -> f32 {
+    1. - (1. - x).powi(power)
+}
+
+fn ease_in_out_pow(x: f32, power: i32) -> f32 {
+    if x < 0.5 {
+        2f32.powi(power - 1) * x.powi(power)
+    } else {
+        1. - (-2. * x + 2.).powi(power) / 2.
+    }
+}
+
+fn ease_in_sine(x: f32) -> f32 {
+    1. - (x * PI / 2.).cos()
+}
+
+fn ease_out_sine(x: f32) -> f32 {
+    (x * PI / 2.).sin()
+}
+
+fn ease_in_out_sine(x: f32) -> f32 {
+    -((PI * x).cos() - 1.) / 2.
+}
+
+fn ease_in_expo(x: f32) -> f32 {
+    if x <= 0. {
+        0.
+    } else {
+        2f32.powf(10. * x - 10.)
+    }
+}
+
+fn ease_out_expo(x: f32) -> f32 {
+    if x >= 1. {
+        1.
+    } else {
+        1. - 2f32.powf(-10. * x)
+    }
+}
+
+fn ease_in_out_expo(x: f32) -> f32 {
+    if x <= 0. {
+        0.
+    } else if x >= 1. {
+        1.
+    } else if x < 0.5 {
+        2f32.powf(20. * x - 10.) / 2.
+    } else {
+        (2. - 2f32.powf(-20. * x + 10.)) / 2.
+    }
+}
+
+fn ease_in_circ(x: f32) -> f32 {
+    1. - (1. - x.powi(2)).max(0.).sqrt()
+}
+
+fn ease_out_circ(x: f32) -> f32 {
+    (1. - (x - 1.).powi(2)).max(0.).sqrt()
+}
+
+fn ease_in_out_circ(x: f32) -> f32 {
+    if x < 0.5 {
+        (1. - (1. - (2. * x).powi(2)).max(0.).sqrt()) / 2.
+    } else {
+        ((1. - (-2. * x + 2.).powi(2)).max(0.).sqrt() + 1.) / 2.
+    }
+}
+
+const BACK_C1: f32 = 1.70158;
+const BACK_C2: f32 = BACK_C1 * 1.525;
+const BACK_C3: f32 = BACK_C1 + 1.;
+
+fn ease_in_back(x: f32) -> f32 {
+    BACK_C3 * x.powi(3) - BACK_C1 * x.powi(2)
+}
+
+fn ease_out_back(x: f32) -> f32 {
+    1. + BACK_C3 * (x - 1.).powi(3) + BACK_C1 * (x - 1.).powi(2)
+}
+
+fn ease_in_out_back(x: f32) -> f32 {
+    if x < 0.5 {
+        ((2. * x).powi(2) * ((BACK_C2 + 1.) * 2. * x - BACK_C2)) / 2.
+    } else {
+        ((2. * x - 2.).powi(2) * ((BACK_C2 + 1.) * (x * 2. - 2.) + BACK_C2) + 2.) / 2.
+    }
+}
+
+const ELASTIC_C4: f32 = (2. * PI) / 3.;
+const ELASTIC_C5: f32 = (2. * PI) / 4.5;
+
+fn ease_in_elastic(x: f32) -> f32 {
+    if x <= 0. {
+        0.
+    } else if x >= 1. {
+        1.
+    } else {
+        -(2f32.powf(10. * x - 10.)) * ((x * 10. - 10.75) * ELASTIC_C4).sin()
+    }
+}
+
+fn ease_out_elastic(x: f32) -> f32 {
+    if x <= 0. {
+        0.
+    } else if x >= 1. {
+        1.
+    } else {
+        2f32.powf(-10. * x) * ((x * 10. - 0.75) * ELASTIC_C4).sin() + 1.
+    }
+}
+
+fn ease_in_out_elastic(x: f32) -> f32 {
+    if x <= 0. {
+        0.
+    } else if x >= 1. {
+        1.
+    } else if x < 0.5 {
+        -(2f32.powf(20. * x - 10.) * ((20. * x - 11.125) * ELASTIC_C5).sin()) / 2.
+    } else {
+        (2f32.powf(-20. * x + 10.) * ((20. * x - 11.125) * ELASTIC_C5).sin()) / 2. + 1.
+    }
+}
+
 fn ease_out_bounce(mut x: f32) -> f32 {
     const N1: f32 = 7.5625;
     const D1: f32 = 2.75;
@@ -166,21 +1007,266 @@ fn ease_out_bounce(mut x: f32) -> f32 {
     }
 }
 
-fn ease_in_circ(x: f32) -> f32 {
-    1. - (1. - x.powf(2.)).sqrt()
+fn ease_in_bounce(x: f32) -> f32 {
+    1. - ease_out_bounce(1. - x)
 }
 
-fn ease_in_out_circ(x: f32) -> f32 {
+fn ease_in_out_bounce(x: f32) -> f32 {
     if x < 0.5 {
-        (1. - (1. - (2. * x).powf(2.)).sqrt()) / 2.0
+        (1. - ease_out_bounce(1. - 2. * x)) / 2.
     } else {
-        ((1. - (-2. * x + 2.).powf(2.).sqrt()) + 1.) / 2.0
+        (1. + ease_out_bounce(2. * x - 1.)) / 2.
     }
 }
 
-fn ease_out_back(x: f32) -> f32 {
-    const C1: f32 = 1.70518;
-    const C3: f32 = C1 + 1.0;
+fn cubic_bezier(x: f32, x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    // Bezier curve through (0, 0), (x1, y1), (x2, y2) and (1, 1), in its polynomial form
+    let curve = |t: f32, p1: f32, p2: f32| {
+        let c = 3. * p1;
+        let b = 3. * (p2 - p1) - c;
+        let a = 1. - c - b;
+        ((a * t + b) * t + c) * t
+    };
+    let slope = |t: f32, p1: f32, p2: f32| {
+        let c = 3. * p1;
+        let b = 3. * (p2 - p1) - c;
+        let a = 1. - c - b;
+        (3. * a * t + 2. * b) * t + c
+    };
+
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+
+    // Find t for the given x, first with Newton's method and if that fails by bisection
+    let mut t = x;
+    for _ in 0..8 {
+        let error = curve(t, x1, x2) - x;
+        if error.abs() < 1e-6 {
+            return curve(t, y1, y2);
+        }
+        let derivative = slope(t, x1, x2);
+        if derivative.abs() < 1e-6 {
+            break;
+        }
+        t -= error / derivative;
+    }
+
+    let (mut low, mut high) = (0f32, 1f32);
+    t = x;
+    while high - low > 1e-6 {
+        if curve(t, x1, x2) < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.;
+    }
+
+    curve(t, y1, y2)
+}
 
-    1. + C3 * (x - 1.0).powf(3.0) + C1 * (x - 1.).powf(2.)
+fn ease_steps(x: f32, steps: u32) -> f32 {
+    let steps = steps.max(1) as f32;
+    if x >= 1. {
+        1.
+    } else {
+        (x * steps).floor().max(0.) / steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Easing, OnFinish, ScaleLens, Tween};
+
+    const ALL: [Easing; 33] = [
+        Easing::Linear,
+        Easing::EaseInQuad,
+        Easing::EaseOutQuad,
+        Easing::EaseInOutQuad,
+        Easing::EaseInCubic,
+        Easing::EaseOutCubic,
+        Easing::EaseInOutCubic,
+        Easing::EaseInQuart,
+        Easing::EaseOutQuart,
+        Easing::EaseInOutQuart,
+        Easing::EaseInQuint,
+        Easing::EaseOutQuint,
+        Easing::EaseInOutQuint,
+        Easing::EaseInSine,
+        Easing::EaseOutSine,
+        Easing::EaseInOutSine,
+        Easing::EaseInExpo,
+        Easing::EaseOutExpo,
+        Easing::EaseInOutExpo,
+        Easing::EaseInCirc,
+        Easing::EaseOutCirc,
+        Easing::EaseInOutCirc,
+        Easing::EaseInBack,
+        Easing::EaseOutBack,
+        Easing::EaseInOutBack,
+        Easing::EaseInElastic,
+        Easing::EaseOutElastic,
+        Easing::EaseInOutElastic,
+        Easing::EaseInBounce,
+        Easing::EaseOutBounce,
+        Easing::EaseInOutBounce,
+        Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        Easing::CubicBezier(0.68, -0.6, 0.32, 1.6),
+    ];
+
+    const SAMPLES: u32 = 10_000;
+    const EPSILON: f32 = 1e-4;
+
+    fn samples() -> impl Iterator<Item = f32> {
+        (0..=SAMPLES).map(|i| i as f32 / SAMPLES as f32)
+    }
+
+    #[test]
+    fn curves_start_at_zero_and_end_at_one() {
+        for easing in ALL.iter().chain(&[Easing::Steps(1), Easing::Steps(4)]) {
+            assert!(
+                easing.ease(0.).abs() < EPSILON,
+                "{:?} maps 0 to {}",
+                easing,
+                easing.ease(0.)
+            );
+            assert!(
+                (easing.ease(1.) - 1.).abs() < EPSILON,
+                "{:?} maps 1 to {}",
+                easing,
+                easing.ease(1.)
+            );
+        }
+    }
+
+    #[test]
+    fn curves_are_continuous() {
+        // The circ curves are vertical at their ends and change by about 0.014 in the last 1/10000
+        const MAX_JUMP: f32 = 0.02;
+
+        for easing in ALL.iter() {
+            let mut previous = easing.ease(0.);
+            for x in samples().skip(1) {
+                let value = easing.ease(x);
+                assert!(value.is_finite(), "{:?} is not finite at {}", easing, x);
+                assert!(
+                    (value - previous).abs() < MAX_JUMP,
+                    "{:?} jumps from {} to {} at {}",
+                    easing,
+                    previous,
+                    value,
+                    x
+                );
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn in_out_curves_are_symmetric() {
+        let pairs = [
+            (Easing::EaseInQuad, Easing::EaseOutQuad),
+            (Easing::EaseInCubic, Easing::EaseOutCubic),
+            (Easing::EaseInQuart, Easing::EaseOutQuart),
+            (Easing::EaseInQuint, Easing::EaseOutQuint),
+            (Easing::EaseInSine, Easing::EaseOutSine),
+            (Easing::EaseInExpo, Easing::EaseOutExpo),
+            (Easing::EaseInCirc, Easing::EaseOutCirc),
+            (Easing::EaseInBack, Easing::EaseOutBack),
+            (Easing::EaseInElastic, Easing::EaseOutElastic),
+            (Easing::EaseInBounce, Easing::EaseOutBounce),
+        ];
+
+        for (ease_in, ease_out) in pairs.iter() {
+            for x in samples() {
+                let mirrored = 1. - ease_out.ease(1. - x);
+                assert!(
+                    (ease_in.ease(x) - mirrored).abs() < 1e-3,
+                    "{:?} and {:?} differ at {}",
+                    ease_in,
+                    ease_out,
+                    x
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ease_in_out_circ_is_monotonic() {
+        let mut previous = 0.;
+        for x in samples() {
+            let value = Easing::EaseInOutCirc.ease(x);
+            assert!(value >= previous, "decreases at {}", x);
+            previous = value;
+        }
+    }
+
+    #[test]
+    fn ease_out_back_overshoots_by_ten_percent() {
+        // With the standard constant the curve peaks at about 1.1
+        let peak = samples()
+            .map(|x| Easing::EaseOutBack.ease(x))
+            .fold(0f32, f32::max);
+        assert!((peak - 1.1).abs() < 1e-3, "peak is {}", peak);
+    }
+
+    #[test]
+    fn linear_cubic_bezier_is_linear() {
+        let easing = Easing::CubicBezier(0.25, 0.25, 0.75, 0.75);
+        for x in samples() {
+            assert!((easing.ease(x) - x).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn steps_jump_at_the_end_of_each_step() {
+        let easing = Easing::Steps(4);
+        assert_eq!(easing.ease(0.2), 0.);
+        assert_eq!(easing.ease(0.25), 0.25);
+        assert_eq!(easing.ease(0.6), 0.5);
+        assert_eq!(easing.ease(0.99), 0.75);
+    }
+
+    #[test]
+    fn queued_tweens_play_after_what_the_tween_continues_with() {
+        let mut running = Tween::<ScaleLens>::new(0., 1., 0.1)
+            .on_finish(OnFinish::Then(Box::new(Sequence::new().wait(0.5))));
+        assert!(running.queue(Tween::new(1., 0., 0.2)).is_ok());
+        assert!(running.queue(Tween::new(1., 0., 0.3)).is_ok());
+
+        match &running.on_finish {
+            OnFinish::Then(next) => {
+                assert_eq!(next.duration(), 0.5);
+                match &next.on_finish {
+                    OnFinish::Then(queued) => {
+                        assert_eq!(queued.duration(), 0.2);
+                        assert!(matches!(queued.on_finish, OnFinish::Then(_)));
+                    }
+                    _ => panic!("nothing is queued after the sequence"),
+                }
+            }
+            _ => panic!("the sequence was dropped"),
+        }
+    }
+
+    #[test]
+    fn queueing_behind_a_despawn_gives_the_tween_back() {
+        let mut running = Tween::<ScaleLens>::new(0., 1., 0.1).on_finish(OnFinish::Despawn);
+
+        assert!(running.queue(Tween::new(1., 0., 0.2)).is_err());
+        assert!(matches!(running.on_finish, OnFinish::Despawn));
+    }
+
+    #[test]
+    fn blending_keeps_what_the_interrupted_tween_does_once_finished() {
+        let mut running = Tween::<ScaleLens>::new(0., 1., 0.1).on_finish(OnFinish::Despawn);
+        running.blend_into(Tween::new(1., 0., 0.2), 0.05);
+
+        assert_eq!(running.duration, 0.2);
+        assert!(matches!(running.on_finish, OnFinish::Despawn));
+    }
 }
@@ -0,0 +1,224 @@
+//! Plays the game in a terminal, with the same rules as the window version
+//!
+//! Useful over SSH or without a GPU: `cargo run --bin squares_tui -- [--arcade] [--seed <SEED>]`.
+
+use bevy_squares::render;
+use bevy_squares::rules::{
+    Board, Combo, MovementDirection, SpawnRng, Spawner, StartingPosition, Tile,
+};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{self, Color},
+    terminal::{self, ClearType},
+};
+use rand::{thread_rng, Rng, SeedableRng};
+use std::io::{self, Write};
+
+const USAGE: &str = "Usage: squares_tui [--arcade] [--seed <SEED>]";
+
+/// The space every cell takes up on screen, including the gap to the next cell
+const CELL_WIDTH: u16 = 9;
+const CELL_HEIGHT: u16 = 4;
+/// Lines above the board, for the score
+const BOARD_TOP: u16 = 2;
+
+struct Game {
+    arcade: bool,
+    fixed_seed: Option<u64>,
+    seed: u64,
+    rng: SpawnRng,
+    board: Board,
+    spawner: Spawner,
+    combo: Combo,
+    score: u64,
+    moves: u32,
+    over: bool,
+}
+
+impl Game {
+    fn new(arcade: bool, fixed_seed: Option<u64>) -> Game {
+        let starting_position = if arcade {
+            StartingPosition::arcade()
+        } else {
+            StartingPosition::classic()
+        };
+        let seed = fixed_seed.unwrap_or_else(|| thread_rng().gen());
+
+        Game {
+            arcade,
+            fixed_seed,
+            seed,
+            rng: SpawnRng::seed_from_u64(seed),
+            board: starting_position.board,
+            spawner: Spawner::new(starting_position.spawn_policy),
+            combo: Combo::new(starting_position.combo_rules),
+            score: 0,
+            moves: 0,
+            over: false,
+        }
+    }
+
+    fn restart(&mut self) {
+        *self = Game::new(self.arcade, self.fixed_seed);
+    }
+
+    /// Scores a move exactly like `move_squares` does for the entities
+    fn play(&mut self, direction: MovementDirection) {
+        if self.over {
+            return;
+        }
+
+        let turn = self.board.play(direction, &mut self.spawner, &mut self.rng);
+        let multiplier = self.combo.apply(&turn);
+        self.score += turn.points() * multiplier;
+
+        if turn.moved() {
+            self.moves += 1;
+        }
+        self.over = turn.stuck;
+    }
+}
+
+/// The colors of the squares of the window version
+fn tile_color(tile: Tile) -> Color {
+    let color = render::tile_color(tile);
+    Color::Rgb {
+        r: color[0],
+        g: color[1],
+        b: color[2],
+    }
+}
+
+fn draw(out: &mut impl Write, game: &Game) -> crossterm::Result<()> {
+    queue!(
+        out,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        style::Print(format!("Score {}   Moves {}", game.score, game.moves))
+    )?;
+    if game.combo.multiplier() > 1 {
+        queue!(
+            out,
+            style::Print(format!("   Combo x{}", game.combo.multiplier()))
+        )?;
+    }
+
+    let size = game.board.size();
+    for row in 0..size {
+        // The board counts rows from the bottom, the terminal from the top
+        let y = size - 1 - row;
+
+        for x in 0..size {
+            let tile = game.board.tile((x, y));
+            let color = tile.map_or(Color::DarkGrey, tile_color);
+            let label = tile.map_or(String::new(), |tile| tile.to_string());
+            let width = (CELL_WIDTH - 1) as usize;
+
+            for line in 0..CELL_HEIGHT - 1 {
+                let text = if line == (CELL_HEIGHT - 1) / 2 {
+                    format!("{:^width$}", label, width = width)
+                } else {
+                    " ".repeat(width)
+                };
+
+                queue!(
+                    out,
+                    cursor::MoveTo(
+                        x as u16 * CELL_WIDTH,
+                        BOARD_TOP + row as u16 * CELL_HEIGHT + line
+                    ),
+                    style::SetBackgroundColor(color),
+                    style::SetForegroundColor(Color::Black),
+                    style::Print(text),
+                    style::ResetColor
+                )?;
+            }
+        }
+    }
+
+    let help = if game.over {
+        "GAME OVER - R restarts, Q quits"
+    } else {
+        "Arrow keys move, R restarts, Q quits"
+    };
+    queue!(
+        out,
+        cursor::MoveTo(0, BOARD_TOP + size as u16 * CELL_HEIGHT),
+        style::Print(help)
+    )?;
+
+    out.flush()
+}
+
+/// Plays until the player quits, returning the last game
+fn run(out: &mut impl Write, mut game: Game) -> crossterm::Result<Game> {
+    loop {
+        draw(out, &game)?;
+
+        if let Event::Key(KeyEvent { code, modifiers }) = event::read()? {
+            match code {
+                KeyCode::Up => game.play(MovementDirection::Up),
+                KeyCode::Down => game.play(MovementDirection::Down),
+                KeyCode::Left => game.play(MovementDirection::Left),
+                KeyCode::Right => game.play(MovementDirection::Right),
+                KeyCode::Char('r') => game.restart(),
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(game),
+                // Raw mode swallows the interrupt, so it has to be handled like any other key
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(game),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn parse_args() -> Result<(bool, Option<u64>), String> {
+    let mut arcade = false;
+    let mut seed = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--arcade" => arcade = true,
+            "--seed" => {
+                let value = args.next().ok_or("`--seed` needs a value")?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid seed `{}`", value))?,
+                );
+            }
+            _ => return Err(format!("unknown option `{}`", arg)),
+        }
+    }
+
+    Ok((arcade, seed))
+}
+
+fn main() -> crossterm::Result<()> {
+    let (arcade, seed) = match parse_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let result = run(&mut stdout, Game::new(arcade, seed));
+
+    // Restore the terminal even if the game failed
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    let game = result?;
+    println!(
+        "Score {} in {} moves (seed {})",
+        game.score, game.moves, game.seed
+    );
+    Ok(())
+}
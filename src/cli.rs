@@ -0,0 +1,238 @@
+use crate::ai::Strategy;
+use crate::theme::Theme;
+use crate::GameMode;
+use bevy_squares::render::Palette;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Smaller texts can't be read, and bigger ones don't fit on the screens anymore
+pub const MIN_TEXT_SCALE: f32 = 0.5;
+pub const MAX_TEXT_SCALE: f32 = 2.;
+
+pub const USAGE: &str = "\
+Usage: bevy_squares [OPTIONS]
+
+Options:
+    --mode <MODE>            classic (default), daily, arcade or puzzle
+    --daily, --arcade, --puzzle
+                             Shorthands for --mode
+    --seed <SEED>            Play every game with the same seed
+    --load <FILE>            Start from the board in FILE, written like the board of a level
+    --replay <FILE>          Watch a replay, like last_replay.txt in the data directory
+    --export-gif <FILE>      Draw the boards of the replay into an animated GIF instead of watching it
+    --ai <STRATEGY>          Let the computer play, greedy or expectimax
+    --theme <THEME>          light (default), dark or high-contrast
+    --palette <PALETTE>      The colors of the squares, hue (default) or colorblind
+    --tile-numbers           Show the score on every square
+    --reduced-motion         Fade squares in and out instead of moving, bouncing and shaking things
+    --text-scale <X>         Make the texts of the interface bigger or smaller, from 0.5 to 2
+    --language <CODE>        The language of the texts, like de or ru, instead of the system one
+    --animation-speed <X>    Speed up or slow down all animations, 0 skips them
+    --no-animations          The same as --animation-speed 0
+    --validate-levels        Check that every level can be solved and exit
+    --help                   Show this message and exit
+
+Options only last for this launch, the settings screen (F1) keeps its choices.";
+
+/// Everything that can be chosen when launching the game
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    pub mode: GameMode,
+    pub seed: Option<u64>,
+    pub load: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub export_gif: Option<PathBuf>,
+    pub ai: Option<Strategy>,
+    pub theme: Theme,
+    /// The language of the system is used if none is given
+    pub language: Option<String>,
+    pub palette: Palette,
+    pub tile_numbers: bool,
+    pub reduced_motion: bool,
+    pub text_scale: f32,
+    pub animation_speed: f32,
+    pub validate_levels: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mode: GameMode::Classic,
+            seed: None,
+            load: None,
+            replay: None,
+            export_gif: None,
+            ai: None,
+            theme: Theme::default(),
+            language: None,
+            palette: Palette::default(),
+            tile_numbers: false,
+            reduced_motion: false,
+            text_scale: 1.,
+            animation_speed: 1.,
+            validate_levels: false,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    /// Parses the arguments, without the name of the program
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        Options::parse_with(Options::default(), args)
+    }
+
+    /// Parses the arguments on top of `defaults`, like the saved settings
+    pub fn parse_with(
+        defaults: Options,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Options, String> {
+        let mut options = defaults;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("`{}` needs a value", arg))
+            };
+
+            match arg.as_str() {
+                "--mode" => options.mode = parse_value(&arg, value()?)?,
+                "--daily" => options.mode = GameMode::Daily,
+                "--arcade" => options.mode = GameMode::Arcade,
+                "--puzzle" => options.mode = GameMode::Puzzle,
+                "--seed" => options.seed = Some(parse_value(&arg, value()?)?),
+                "--load" => options.load = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--export-gif" => options.export_gif = Some(PathBuf::from(value()?)),
+                "--ai" => options.ai = Some(parse_value(&arg, value()?)?),
+                "--theme" => options.theme = parse_value(&arg, value()?)?,
+                "--language" => options.language = Some(value()?),
+                "--palette" => options.palette = parse_value(&arg, value()?)?,
+                "--tile-numbers" => options.tile_numbers = true,
+                "--reduced-motion" => options.reduced_motion = true,
+                "--text-scale" => options.text_scale = parse_value(&arg, value()?)?,
+                "--animation-speed" => options.animation_speed = parse_value(&arg, value()?)?,
+                "--no-animations" => options.animation_speed = 0.,
+                "--validate-levels" => options.validate_levels = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option `{}`", arg)),
+            }
+        }
+
+        if options.seed.is_some() && options.mode == GameMode::Daily {
+            return Err("the daily challenge has its own seed, `--seed` can not be used".into());
+        }
+
+        if options.replay.is_some()
+            && (options.seed.is_some() || options.load.is_some() || options.ai.is_some())
+        {
+            return Err("a replay can not be combined with `--seed`, `--load` or `--ai`".into());
+        }
+
+        if options.export_gif.is_some() && options.replay.is_none() {
+            return Err("`--export-gif` needs a game to export with `--replay`".into());
+        }
+
+        if !(MIN_TEXT_SCALE..=MAX_TEXT_SCALE).contains(&options.text_scale) {
+            return Err(format!(
+                "`--text-scale` must be between {} and {}",
+                MIN_TEXT_SCALE, MAX_TEXT_SCALE
+            ));
+        }
+
+        if !options.animation_speed.is_finite() || options.animation_speed < 0. {
+            return Err("`--animation-speed` must be 0 or more".into());
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_value<T>(name: &str, value: String) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| format!("invalid value `{}` for `{}`: {}", value, name, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_give_the_defaults() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn options_take_their_values() {
+        let options = parse(&[
+            "--mode",
+            "arcade",
+            "--seed",
+            "42",
+            "--ai",
+            "expectimax",
+            "--theme",
+            "dark",
+            "--language",
+            "de",
+            "--palette",
+            "colorblind",
+            "--reduced-motion",
+            "--text-scale",
+            "1.5",
+            "--no-animations",
+        ])
+        .unwrap();
+
+        assert_eq!(options.mode, GameMode::Arcade);
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.ai, Some(Strategy::Expectimax));
+        assert_eq!(options.theme, Theme::Dark);
+        assert_eq!(options.language, Some("de".to_string()));
+        assert_eq!(options.palette, Palette::Colorblind);
+        assert!(options.reduced_motion);
+        assert_eq!(options.text_scale, 1.5);
+        assert_eq!(options.animation_speed, 0.);
+    }
+
+    #[test]
+    fn arguments_override_the_defaults() {
+        let defaults = Options {
+            theme: Theme::Dark,
+            animation_speed: 2.,
+            ..Options::default()
+        };
+        let args = vec!["--theme".to_string(), "light".to_string()];
+        let options = Options::parse_with(defaults, args).unwrap();
+
+        assert_eq!(options.theme, Theme::Light);
+        assert_eq!(options.animation_speed, 2.);
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--seed", "soon"]).is_err());
+        assert!(parse(&["--mode", "endless"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+        assert!(parse(&["--daily", "--seed", "1"]).is_err());
+        assert!(parse(&["--replay", "game.txt", "--ai", "greedy"]).is_err());
+        assert!(parse(&["--export-gif", "game.gif"]).is_err());
+        assert!(parse(&["--text-scale", "5"]).is_err());
+        assert!(parse(&["--palette", "rainbow"]).is_err());
+        assert!(parse(&["--animation-speed", "-1"]).is_err());
+        assert!(parse(&["--animation-speed", "NaN"]).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{Dying, GameSession, GameSquare, Grid, GridPosition, GRID_SIZE};
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Checks every frame that the `Grid` and the square entities agree with each other
+///
+/// Both are kept in sync by hand, so this catches systems that update one but not the other. It is
+/// only added to debug builds.
+#[derive(Default)]
+pub struct ConsistencyPlugin;
+
+impl Plugin for ConsistencyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GridConsistency>()
+            .add_system_to_stage(bevy::app::stage::LAST, check_grid_consistency.system());
+    }
+}
+
+/// The problems found in the last frame, each described in a single line
+#[derive(Default)]
+pub struct GridConsistency {
+    pub problems: Vec<String>,
+}
+
+/// Compares the grid with the squares, given as their entity, position and whether they are dying
+///
+/// Squares take their tile and score from the grid, so a square in the right cell also has the
+/// right score.
+pub fn find_problems(
+    grid: &Grid,
+    squares: impl Iterator<Item = (Entity, GridPosition, bool)>,
+) -> Vec<String> {
+    let mut problems = vec![];
+    let mut live = HashMap::new();
+    let mut dying = HashSet::new();
+
+    for (entity, position, is_dying) in squares {
+        if is_dying {
+            dying.insert(entity);
+        } else {
+            live.insert(entity, position);
+        }
+    }
+
+    for x in 0..GRID_SIZE {
+        for y in 0..GRID_SIZE {
+            let square = match grid.get_at((x, y)) {
+                Some(square) => square,
+                None => continue,
+            };
+
+            if dying.contains(&square.entity) {
+                problems.push(format!(
+                    "Cell ({}, {}) holds {} of {:?}, which is dying",
+                    x, y, square.tile, square.entity
+                ));
+                continue;
+            }
+
+            match live.get(&square.entity) {
+                None => problems.push(format!(
+                    "Cell ({}, {}) holds {} of {:?}, which is not a square",
+                    x, y, square.tile, square.entity
+                )),
+                Some(position) if *position != GridPosition(x, y) => problems.push(format!(
+                    "Cell ({}, {}) holds {} of {:?}, which is at ({}, {})",
+                    x, y, square.tile, square.entity, position.0, position.1
+                )),
+                Some(_) => (),
+            }
+        }
+    }
+
+    for (entity, position) in live {
+        match grid.get_at((position.0, position.1)) {
+            None => problems.push(format!(
+                "{:?} at ({}, {}) is missing from the grid",
+                entity, position.0, position.1
+            )),
+            Some(square) if square.entity != entity => problems.push(format!(
+                "{:?} at ({}, {}) is missing from the grid, its cell holds {:?}",
+                entity, position.0, position.1, square.entity
+            )),
+            Some(_) => (),
+        }
+    }
+
+    problems.sort();
+    problems
+}
+
+fn check_grid_consistency(
+    grid: Res<Grid>,
+    session: Res<GameSession>,
+    mut consistency: ResMut<GridConsistency>,
+    mut square_query: Query<With<GameSquare, (Entity, &GridPosition, Option<&Dying>)>>,
+) {
+    let mut squares = vec![];
+    for (entity, position, dying) in &mut square_query.iter() {
+        squares.push((entity, *position, dying.is_some()));
+    }
+    let problems = find_problems(&grid, squares.into_iter());
+
+    // Only report changes, instead of the same problems every frame
+    if problems != consistency.problems {
+        if problems.is_empty() {
+            eprintln!("Grid and squares agree again");
+        } else {
+            eprintln!(
+                "Grid and squares disagree after {} moves (last move {:?}):",
+                session.stats.moves,
+                session.history.last()
+            );
+            for problem in &problems {
+                eprintln!("  {}", problem);
+            }
+        }
+    }
+
+    consistency.problems = problems;
+}
@@ -0,0 +1,59 @@
+use crate::{GameMode, GameSession, GameState, Grid, LoadPosition, RunningGameState, ScoreState};
+use bevy::prelude::*;
+use clipboard::{ClipboardContext, ClipboardProvider};
+
+/// Ctrl+C copies the current position as text, Ctrl+V loads a copied one
+///
+/// Positions are written in the notation of `bevy_squares::notation`, so they can be pasted into
+/// bug reports and back into the game.
+#[derive(Default)]
+pub struct CopyPastePlugin;
+
+impl Plugin for CopyPastePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(copy_paste_system.system());
+    }
+}
+
+fn copy_paste_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    game_mode: Res<GameMode>,
+    game_state: Res<GameState>,
+    grid: Res<Grid>,
+    score: Res<ScoreState>,
+    session: Res<GameSession>,
+    mut load_events: ResMut<Events<LoadPosition>>,
+) {
+    let control =
+        keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    // Only the board itself can be copied, not the menus covering it
+    let on_board = game_state.state == RunningGameState::Running
+        || game_state.state == RunningGameState::GameOver;
+
+    if !control || !on_board {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::C) {
+        let notation = grid.to_notation(score.score, session.stats.moves, session.rng.seed);
+        let result = ClipboardContext::new().and_then(|mut context| context.set_contents(notation));
+        if let Err(err) = result {
+            eprintln!("Could not copy the position: {}", err);
+        }
+    } else if keyboard_input.just_pressed(KeyCode::V) {
+        // Puzzles are judged against their level and the daily challenge against its board, so
+        // they can't start from another position
+        if *game_mode == GameMode::Puzzle || *game_mode == GameMode::Daily {
+            return;
+        }
+
+        let position = ClipboardContext::new()
+            .and_then(|mut context| context.get_contents())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| Grid::parse_notation(&contents));
+        match position {
+            Ok(position) => load_events.send(LoadPosition(position)),
+            Err(err) => eprintln!("Could not paste the position: {}", err),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::hint::HintSettings;
+use crate::localization::LocalizedText;
+use crate::rules::{Tile, TileKind};
+use crate::{storage, GameMode, GameSession, Grid, RunningGameState, ScoreState, GRID_SIZE};
+use bevy::prelude::*;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LEADERBOARD_FILE: &str = "daily_leaderboard.txt";
+const RESULT_FILE: &str = "daily_result.txt";
+const LEADERBOARD_SHOWN: usize = 5;
+
+#[derive(Default)]
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Added before the summary, which shows the result
+        app.init_resource::<DailyChallenge>()
+            .add_system(record_daily_result.system());
+    }
+}
+
+/// A calendar day in UTC, so that every player shares the same daily challenge
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|it| it.as_secs())
+            .unwrap_or(0);
+
+        Date::from_days_since_epoch((seconds / 86_400) as i64)
+    }
+
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    fn from_days_since_epoch(days: i64) -> Date {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Date { year, month, day }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.year as u64 * 10_000 + self.month as u64 * 100 + self.day as u64
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Default)]
+pub struct DailyChallenge {
+    /// The result of the last finished daily game, none for replays and edited games
+    pub result: Option<DailyResult>,
+    event_reader: EventReader<RunningGameState>,
+}
+
+/// How a finished daily game compares to the other results of the day
+#[derive(Clone, Debug)]
+pub struct DailyResult {
+    /// The rank and the number of results, none if hints kept it off the leaderboard
+    pub rank: Option<(usize, usize)>,
+    pub hints: u32,
+    /// The best scores of the day, best first
+    pub best: Vec<u64>,
+}
+
+impl DailyResult {
+    /// The lines shown on the summary screen
+    pub fn lines(&self) -> Vec<LocalizedText> {
+        let mut lines = vec![match self.rank {
+            Some((rank, players)) => LocalizedText::new("daily-rank")
+                .with_arg("rank", rank)
+                .with_arg("players", players),
+            None => LocalizedText::new("daily-disqualified").with_arg("hints", self.hints),
+        }];
+
+        if !self.best.is_empty() {
+            let best: Vec<String> = self.best.iter().map(|score| score.to_string()).collect();
+            lines.push(LocalizedText::new("daily-best").with_arg("scores", best.join(", ")));
+        }
+        lines.push(LocalizedText::new("daily-shared").with_arg("file", RESULT_FILE));
+        lines
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DailyEntry {
+    pub date: String,
+    pub score: u64,
+    pub moves: u32,
+}
+
+impl DailyEntry {
+    fn parse(line: &str) -> Option<DailyEntry> {
+        let mut parts = line.split('\t');
+        let date = parts.next()?.to_string();
+        let score = parts.next()?.parse().ok()?;
+        let moves = parts.next()?.parse().ok()?;
+
+        Some(DailyEntry { date, score, moves })
+    }
+}
+
+/// Loads all recorded results for the given day, best first
+pub fn load_leaderboard(date: Date) -> Vec<DailyEntry> {
+    let date = date.to_string();
+    let contents = storage::data_file(LEADERBOARD_FILE)
+        .and_then(std::fs::read_to_string)
+        .unwrap_or_default();
+
+    let mut entries: Vec<DailyEntry> = contents
+        .lines()
+        .filter_map(DailyEntry::parse)
+        .filter(|entry| entry.date == date)
+        .collect();
+
+    entries.sort_by(|a, b| b.score.cmp(&a.score).then(a.moves.cmp(&b.moves)));
+    entries
+}
+
+fn save_entry(entry: &DailyEntry) -> std::io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(storage::data_file(LEADERBOARD_FILE)?)?;
+
+    writeln!(file, "{}\t{}\t{}", entry.date, entry.score, entry.moves)
+}
+
+fn square_emoji(tile: Option<Tile>) -> char {
+    const TIERS: [char; 7] = ['🟦', '🟩', '🟨', '🟧', '🟥', '🟪', '🟫'];
+
+    match tile {
+        None => '⬜',
+        Some(tile) if tile.kind == TileKind::Wall => '⬛',
+        Some(tile) => {
+            let tier = (63 - tile.score.max(1).leading_zeros()) as usize / 2;
+            TIERS[tier.min(TIERS.len() - 1)]
+        }
+    }
+}
+
+/// A shareable summary of a daily run, with the final board drawn as emoji
+pub fn result_string(date: Date, score: u64, moves: u32, grid: &Grid) -> String {
+    let mut result = format!(
+        "Squares Daily {}\nScore {} in {} moves\n",
+        date, score, moves
+    );
+
+    for y in (0..GRID_SIZE).rev() {
+        for x in 0..GRID_SIZE {
+            result.push(square_emoji(grid.get_at((x, y)).map(|square| square.tile)));
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+fn record_daily_result(
+    game_mode: Res<GameMode>,
+    grid: Res<Grid>,
+    score: Res<ScoreState>,
+    session: Res<GameSession>,
+    hint_settings: Res<HintSettings>,
+    mut daily_challenge: ResMut<DailyChallenge>,
+    game_events: Res<Events<RunningGameState>>,
+) {
+    let game_over = daily_challenge
+        .event_reader
+        .iter(&game_events)
+        .any(|event| *event == RunningGameState::GameOver);
+
+    if !game_over {
+        return;
+    }
+
+    daily_challenge.result = None;
+
+    // A replay repeats a result that was already recorded, and an edited game isn't the challenge
+    let date = match session.rng.daily {
+        Some(date) if *game_mode == GameMode::Daily && session.is_comparable() => date,
+        _ => return,
+    };
+
+    let entry = DailyEntry {
+        date: date.to_string(),
+        score: score.score,
+        moves: session.stats.moves,
+    };
+
+    let disqualified = hint_settings.disqualifies(session.stats.hints);
+    if !disqualified {
+        if let Err(err) = save_entry(&entry) {
+            eprintln!("Could not save daily result: {}", err);
+        }
+    }
+
+    let result = result_string(date, entry.score, entry.moves, &grid);
+    if let Err(err) = storage::data_file(RESULT_FILE).and_then(|path| std::fs::write(path, &result))
+    {
+        eprintln!("Could not export daily result: {}", err);
+    }
+
+    let leaderboard = load_leaderboard(date);
+    let rank = if disqualified {
+        None
+    } else {
+        let rank = leaderboard
+            .iter()
+            .position(|it| *it == entry)
+            .unwrap_or(leaderboard.len());
+        Some((rank + 1, leaderboard.len().max(rank + 1)))
+    };
+
+    daily_challenge.result = Some(DailyResult {
+        rank,
+        hints: session.stats.hints,
+        best: leaderboard
+            .iter()
+            .take(LEADERBOARD_SHOWN)
+            .map(|it| it.score)
+            .collect(),
+    });
+}
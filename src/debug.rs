@@ -0,0 +1,329 @@
+use crate::animation::{self, Sequence, Tween};
+use crate::rules::{SpawnPolicy, Spawner, Tile, TileKind};
+use crate::text_sprite::{TextSprite, TextSpriteSize};
+use crate::{
+    calculate_grid_position, spawn_square, GameSession, Grid, SquareColors, GRID_SIZE, SQUARE_WIDTH,
+};
+use bevy::prelude::*;
+use bevy::window::CursorMoved;
+
+/// Squares cycle up to this score when clicked in the editor, and then back to an empty cell
+const EDITOR_MAX_SCORE: u64 = 2048;
+const LABEL_FONT_SIZE: f32 = 14.0;
+const INFO_LINES: usize = 4;
+
+/// A developer overlay showing the internals of the board, toggled with F3
+///
+/// F4 additionally turns on the editor, in which clicking on cells changes their squares and N
+/// lines up the hovered square as the next spawn. This makes it easy to set up a specific board
+/// instead of playing until it happens. Edited games don't count for records or the leaderboard.
+#[derive(Default)]
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DebugState>()
+            .add_system(toggle_debug.system())
+            .add_system(track_cursor.system())
+            .add_system(edit_board.system())
+            .add_system(show_overlay.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, update_cell_labels.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, update_info.system());
+    }
+}
+
+#[derive(Default)]
+struct DebugState {
+    visible: bool,
+    editing: bool,
+    /// Whether the overlay entities currently exist
+    shown: bool,
+    /// The cursor position in window coordinates, from the bottom left
+    cursor: Option<Vec2>,
+    cursor_reader: EventReader<CursorMoved>,
+}
+
+/// Marks all entities making up the overlay
+struct DebugOverlay;
+
+#[derive(Clone, Copy)]
+enum CellLabel {
+    Index(u32, u32),
+    Content(u32, u32),
+}
+
+struct InfoLine(usize);
+
+fn toggle_debug(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<DebugState>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        state.visible = !state.visible;
+        state.editing = state.editing && state.visible;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        state.editing = !state.editing;
+        state.visible = state.visible || state.editing;
+    }
+}
+
+fn track_cursor(mut state: ResMut<DebugState>, cursor_events: Res<Events<CursorMoved>>) {
+    if let Some(event) = state.cursor_reader.latest(&cursor_events) {
+        state.cursor = Some(event.position);
+    }
+}
+
+/// The cell under the cursor, if any
+fn hovered_cell(cursor: Vec2, windows: &Windows) -> Option<(u32, u32)> {
+    let window = windows.get_primary()?;
+    // The board is centered in the window
+    let position = cursor - Vec2::new(window.width as f32, window.height as f32) / 2.;
+
+    (0..GRID_SIZE)
+        .flat_map(|x| (0..GRID_SIZE).map(move |y| (x, y)))
+        .find(|&(x, y)| {
+            let (left, bottom) = calculate_grid_position(x as i32, y as i32);
+            position.x() >= left
+                && position.x() < left + SQUARE_WIDTH as f32
+                && position.y() >= bottom
+                && position.y() < bottom + SQUARE_WIDTH as f32
+        })
+}
+
+/// The next square in the cycle of the editor, from empty over rising scores back to empty
+fn next_tile(tile: Option<Tile>) -> Option<Tile> {
+    match tile {
+        None => Some(Tile::normal(1)),
+        Some(tile) if tile.kind == TileKind::Wall || tile.kind == TileKind::Wildcard => None,
+        Some(tile) if tile.score >= EDITOR_MAX_SCORE => None,
+        Some(tile) => Some(Tile {
+            score: tile.score * 2,
+            ..tile
+        }),
+    }
+}
+
+fn edit_board(
+    mut commands: Commands,
+    state: Res<DebugState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    colors: Res<SquareColors>,
+    mut session: ResMut<GameSession>,
+    mut grid: ResMut<Grid>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !state.editing {
+        return;
+    }
+
+    let position = match state
+        .cursor
+        .and_then(|cursor| hovered_cell(cursor, &windows))
+    {
+        Some(position) => position,
+        None => return,
+    };
+    let current = grid.get_at(position).map(|square| square.tile);
+    // Special squares keep the score of the square they replace
+    let score = current
+        .map(|tile| tile.score)
+        .filter(|&score| score > 0)
+        .unwrap_or(1);
+
+    if keyboard_input.just_pressed(KeyCode::N) {
+        // Spawns follow the lined up squares until they run out, then stop
+        let tile = current.unwrap_or_else(|| Tile::normal(1));
+        match &mut session.spawner.policy {
+            SpawnPolicy::Sequence(tiles) => tiles.push(tile),
+            _ => session.spawner = Spawner::new(SpawnPolicy::Sequence(vec![tile])),
+        }
+        session.stats.edited = true;
+        return;
+    }
+
+    let tile = if mouse_input.just_pressed(MouseButton::Left) {
+        next_tile(current)
+    } else if mouse_input.just_pressed(MouseButton::Right) {
+        None
+    } else if keyboard_input.just_pressed(KeyCode::W) {
+        Some(Tile::wall())
+    } else if keyboard_input.just_pressed(KeyCode::J) {
+        Some(Tile::wildcard())
+    } else if keyboard_input.just_pressed(KeyCode::B) {
+        Some(Tile {
+            kind: TileKind::Bomb,
+            score,
+        })
+    } else if keyboard_input.just_pressed(KeyCode::X) {
+        Some(Tile {
+            kind: TileKind::Multiplier,
+            score,
+        })
+    } else {
+        return;
+    };
+
+    session.stats.edited = true;
+
+    // Squares are built differently depending on their kind, so they are replaced entirely
+    if let Some(square) = grid.take_at(position) {
+        commands.despawn_recursive(square.entity);
+    }
+
+    if let Some(tile) = tile {
+        spawn_square(
+            &mut commands,
+            &mut grid,
+            &mut meshes,
+            &colors,
+            position,
+            tile,
+            None,
+        );
+    }
+}
+
+fn show_overlay(
+    mut commands: Commands,
+    mut state: ResMut<DebugState>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut overlay_query: Query<With<DebugOverlay, Entity>>,
+) {
+    if state.visible == state.shown {
+        return;
+    }
+    state.shown = state.visible;
+
+    if !state.visible {
+        for entity in &mut overlay_query.iter() {
+            commands.despawn_recursive(entity);
+        }
+        return;
+    }
+
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+
+    for x in 0..GRID_SIZE {
+        for y in 0..GRID_SIZE {
+            let (left, bottom) = calculate_grid_position(x as i32, y as i32);
+            let center = left + SQUARE_WIDTH as f32 / 2.;
+            let labels = [
+                (CellLabel::Index(x, y), bottom + SQUARE_WIDTH as f32 - 14.),
+                (CellLabel::Content(x, y), bottom + 14.),
+            ];
+
+            for &(label, label_y) in labels.iter() {
+                commands.spawn((
+                    DebugOverlay,
+                    label,
+                    TextSprite {
+                        text: String::new(),
+                        text_style: TextStyle {
+                            font_size: LABEL_FONT_SIZE,
+                            color: Color::BLACK,
+                        },
+                        font: font_handle.clone(),
+                    },
+                    TextSpriteSize::default(),
+                    Translation::new(center, label_y, 20.),
+                    Draw {
+                        is_transparent: true,
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+    }
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with(DebugOverlay)
+        .with_children(|parent| {
+            for line in 0..INFO_LINES {
+                parent
+                    .spawn(TextComponents {
+                        text: Text {
+                            value: String::new(),
+                            font: font_handle.clone(),
+                            style: TextStyle {
+                                font_size: LABEL_FONT_SIZE,
+                                color: Color::BLACK,
+                            },
+                        },
+                        ..Default::default()
+                    })
+                    .with(InfoLine(line));
+            }
+        });
+}
+
+fn update_cell_labels(grid: Res<Grid>, mut label_query: Query<(&CellLabel, &mut TextSprite)>) {
+    for (label, mut text_sprite) in &mut label_query.iter() {
+        let text = match *label {
+            CellLabel::Index(x, y) => format!("{},{}", x, y),
+            CellLabel::Content(x, y) => match grid.get_at((x, y)) {
+                Some(square) => format!("{} #{}", square.tile, square.entity.id()),
+                None => String::new(),
+            },
+        };
+
+        // Only touch the text when it changes, as every change lays out the glyphs again
+        if text_sprite.text != text {
+            text_sprite.text = text;
+        }
+    }
+}
+
+fn update_info(
+    state: Res<DebugState>,
+    session: Res<GameSession>,
+    mut info_query: Query<(&InfoLine, &mut Text)>,
+    mut translation_tweens: Query<&Tween<animation::TranslationLens>>,
+    mut scale_tweens: Query<&Tween<animation::ScaleLens>>,
+    mut sequences: Query<&Sequence>,
+) {
+    if !state.shown {
+        return;
+    }
+
+    let moving = translation_tweens.iter().iter().count();
+    let scaling = scale_tweens.iter().iter().count();
+    let sequenced = sequences.iter().iter().count();
+
+    for (line, mut text) in &mut info_query.iter() {
+        let value = match line.0 {
+            0 => format!("Seed {}, {} moves", session.rng.seed, session.stats.moves),
+            1 => format!(
+                "Tweens: {} moving, {} scaling, {} sequences",
+                moving, scaling, sequenced
+            ),
+            2 if state.editing => "EDITOR click: next square, right click: clear".to_string(),
+            2 => "F4 edits the board".to_string(),
+            3 if state.editing => {
+                "W: wall, J: wildcard, B: bomb, X: multiplier, N: spawn next".to_string()
+            }
+            _ => String::new(),
+        };
+
+        if text.value != value {
+            text.value = value;
+        }
+    }
+}
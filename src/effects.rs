@@ -0,0 +1,292 @@
+use crate::animation::{self, AnimationSettings, Easing, OnFinish, Sequence, Tween};
+use crate::text_sprite::{TextSprite, TextSpriteColorLens, TextSpriteSize};
+use crate::theme::Theme;
+use crate::{
+    calculate_grid_position, MainCamera, SquareColors, SquareEvent, SLIDE_DURATION, SQUARE_WIDTH,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use rand::{thread_rng, Rng};
+
+/// Merges of squares with at least this score burst into particles
+const PARTICLE_MIN_SCORE: u64 = 32;
+const PARTICLE_RADIUS: f32 = 6.0;
+const PARTICLE_DURATION: f32 = 0.5;
+/// How far the screen shakes per doubling of the points of a move
+const SHAKE_PER_DOUBLING: f32 = 1.5;
+const MAX_SHAKE: f32 = 12.0;
+const SHAKE_STEPS: u32 = 6;
+const SHAKE_STEP_DURATION: f32 = 0.03;
+const POPUP_FONT_SIZE: f32 = 32.0;
+const POPUP_RISE: f32 = 60.0;
+const POPUP_DURATION: f32 = 0.8;
+/// Popups from the same move start a little later and higher each, so they never cover each other
+const POPUP_STAGGER_DELAY: f32 = 0.05;
+const POPUP_STAGGER_OFFSET: f32 = 16.0;
+
+#[derive(Default)]
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<EffectsSettings>()
+            .init_resource::<EffectsState>()
+            .add_system(play_effects.system());
+    }
+}
+
+/// Which effects play, chosen on the settings screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EffectsSettings {
+    /// Squares pop up a little when merged into or spawned
+    pub pop: bool,
+    /// Big merges burst into particles in the color of the square
+    pub particles: bool,
+    /// The screen shakes with each merge, the more points the stronger
+    pub screen_shake: bool,
+    /// The points of each merge rise from where it happened
+    pub score_popups: bool,
+}
+
+impl Default for EffectsSettings {
+    fn default() -> EffectsSettings {
+        EffectsSettings {
+            pop: true,
+            particles: true,
+            screen_shake: true,
+            score_popups: true,
+        }
+    }
+}
+
+struct EffectsState {
+    event_reader: EventReader<SquareEvent>,
+    /// Loaded once for all score popups
+    font: Handle<Font>,
+}
+
+impl FromResources for EffectsState {
+    fn from_resources(resources: &Resources) -> EffectsState {
+        let asset_server = resources.get::<AssetServer>().unwrap();
+        EffectsState {
+            event_reader: Default::default(),
+            font: asset_server
+                .load("assets/bungee_inline_regular.ttf")
+                .unwrap(),
+        }
+    }
+}
+
+/// Pops, particles and shaking all move things, so reduced motion turns them off
+fn play_effects(
+    mut commands: Commands,
+    settings: Res<EffectsSettings>,
+    animation_settings: Res<AnimationSettings>,
+    mut state: ResMut<EffectsState>,
+    square_events: Res<Events<SquareEvent>>,
+    colors: Res<SquareColors>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    theme: Res<Theme>,
+    mut camera_query: Query<With<MainCamera, (Entity, &Translation)>>,
+) {
+    let mut points = 0;
+    let mut popups = 0;
+    let moving = !animation_settings.reduced_motion;
+    let font = state.font.clone();
+
+    for event in state.event_reader.iter(&square_events) {
+        match *event {
+            SquareEvent::Merged {
+                entity,
+                position,
+                tile,
+                points: merge_points,
+                ..
+            } => {
+                points += merge_points;
+
+                if settings.pop && moving {
+                    commands.insert_one(
+                        entity,
+                        Tween::<animation::ScaleLens>::new(1., 1.2, 0.08)
+                            .with_delay(SLIDE_DURATION)
+                            .with_ease(Easing::EaseOutQuad)
+                            .with_repeat(animation::Repeat::Times(1))
+                            .with_ping_pong(),
+                    );
+                }
+
+                if settings.score_popups {
+                    spawn_score_popup(
+                        &mut commands,
+                        font.clone(),
+                        theme.text(),
+                        position,
+                        merge_points,
+                        popups,
+                    );
+                    popups += 1;
+                }
+
+                if settings.particles && moving && tile.score >= PARTICLE_MIN_SCORE {
+                    spawn_particles(
+                        &mut commands,
+                        &mut meshes,
+                        colors.tile_color(tile),
+                        position,
+                        tile.score,
+                    );
+                }
+            }
+            SquareEvent::Spawned { entity } => {
+                if settings.pop && moving {
+                    commands.insert_one(
+                        entity,
+                        Tween::<animation::ScaleLens>::new(0.5, 1., 0.2)
+                            .with_ease(Easing::EaseOutBack),
+                    );
+                }
+            }
+            SquareEvent::Cleared { .. } => {}
+        }
+    }
+
+    if settings.screen_shake && moving && points > 0 {
+        let strength = (SHAKE_PER_DOUBLING * (64 - points.leading_zeros()) as f32).min(MAX_SHAKE);
+
+        for (camera, translation) in &mut camera_query.iter() {
+            // The camera is never moved otherwise, so it rests at the origin
+            let rest = Vec3::new(0., 0., translation.0.z());
+            commands.insert_one(camera, shake(rest, strength));
+        }
+    }
+}
+
+fn spawn_particles(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    material: Handle<ColorMaterial>,
+    position: (u32, u32),
+    score: u64,
+) {
+    let mut rng = thread_rng();
+    let (x, y) = calculate_grid_position(position.0 as i32, position.1 as i32);
+    let center = Vec3::new(
+        x + SQUARE_WIDTH as f32 / 2.,
+        y + SQUARE_WIDTH as f32 / 2.,
+        2.,
+    );
+    let count = 4 + 2 * (64 - score.leading_zeros());
+
+    for _ in 0..count {
+        let angle = rng.gen_range(0., 2. * std::f32::consts::PI);
+        let distance = rng.gen_range(0.4, 1.0) * SQUARE_WIDTH as f32;
+        let target = center + Vec3::new(angle.cos(), angle.sin(), 0.) * distance;
+
+        // Hidden until the merging squares meet
+        let burst = Sequence::new()
+            .wait(SLIDE_DURATION)
+            .then(
+                Tween::<animation::TranslationLens>::new(center, target, PARTICLE_DURATION)
+                    .with_ease(Easing::EaseOutCubic),
+            )
+            .together(
+                Tween::<animation::ScaleLens>::new(1., 0., PARTICLE_DURATION)
+                    .with_ease(Easing::EaseInQuad),
+            )
+            .on_finish(OnFinish::Despawn);
+
+        commands
+            .spawn(primitive(
+                material.clone(),
+                meshes,
+                ShapeType::Circle(PARTICLE_RADIUS),
+                TessellationMode::Fill(&FillOptions::default()),
+                center.into(),
+            ))
+            .with(Scale(0.))
+            .with(Draw {
+                is_transparent: true,
+                ..Default::default()
+            })
+            .with(burst);
+    }
+}
+
+fn spawn_score_popup(
+    commands: &mut Commands,
+    font: Handle<Font>,
+    color: Color,
+    position: (u32, u32),
+    points: u64,
+    index: u32,
+) {
+    let (x, y) = calculate_grid_position(position.0 as i32, position.1 as i32);
+    let start = Vec3::new(
+        x + SQUARE_WIDTH as f32 / 2.,
+        y + SQUARE_WIDTH as f32 / 2. + index as f32 * POPUP_STAGGER_OFFSET,
+        3.,
+    );
+    // Invisible until the merging squares meet
+    let popup = Sequence::new()
+        .wait(SLIDE_DURATION + index as f32 * POPUP_STAGGER_DELAY)
+        .then(
+            Tween::<animation::TranslationLens>::new(
+                start,
+                start + Vec3::new(0., POPUP_RISE, 0.),
+                POPUP_DURATION,
+            )
+            .with_ease(Easing::EaseOutCubic),
+        )
+        .together(
+            Tween::<TextSpriteColorLens>::new(
+                color,
+                Color::rgba(color.r, color.g, color.b, 0.),
+                POPUP_DURATION,
+            )
+            .with_ease(Easing::EaseInQuad),
+        )
+        .on_finish(OnFinish::Despawn);
+
+    commands.spawn((
+        TextSprite {
+            text: format!("+{}", points),
+            text_style: TextStyle {
+                font_size: POPUP_FONT_SIZE,
+                color: Color::rgba(color.r, color.g, color.b, 0.),
+            },
+            font,
+        },
+        TextSpriteSize::default(),
+        Translation(start),
+        Draw {
+            is_transparent: true,
+            ..Default::default()
+        },
+        popup,
+    ));
+}
+
+/// Jolts the camera around `rest` a few times, calming down towards the end
+fn shake(rest: Vec3, strength: f32) -> Sequence {
+    let mut rng = thread_rng();
+    let mut sequence = Sequence::new();
+    let mut from = rest;
+
+    for step in 0..SHAKE_STEPS {
+        let falloff = 1. - step as f32 / SHAKE_STEPS as f32;
+        let angle = rng.gen_range(0., 2. * std::f32::consts::PI);
+        let to = rest + Vec3::new(angle.cos(), angle.sin(), 0.) * strength * falloff;
+
+        sequence = sequence.then(
+            Tween::<animation::TranslationLens>::new(from, to, SHAKE_STEP_DURATION)
+                .with_ease(Easing::Linear),
+        );
+        from = to;
+    }
+
+    sequence.then(
+        Tween::<animation::TranslationLens>::new(from, rest, SHAKE_STEP_DURATION)
+            .with_ease(Easing::Linear),
+    )
+}
@@ -0,0 +1,205 @@
+use crate::rules::{Board, MovementDirection};
+use crate::settings::KeyBindings;
+use crate::{
+    animation, calculate_grid_position, GameSession, GameState, Grid, RunningGameState, GRID_SIZE,
+    SQUARE_MARGIN, SQUARE_WIDTH,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+/// How many moves the hint looks ahead
+const HINT_DEPTH: u32 = 3;
+/// How much a free cell at the end of the lookahead is worth compared to points
+const EMPTY_CELL_WEIGHT: f32 = 4.0;
+const HINT_DURATION: f32 = 1.5;
+
+#[derive(Default)]
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<HintSettings>()
+            .add_system(request_hint.system())
+            .add_system(update_hint_arrows.system());
+    }
+}
+
+pub struct HintSettings {
+    /// Whether a game in which hints were used is kept out of the records and the leaderboard
+    pub disqualifies_high_score: bool,
+}
+
+impl HintSettings {
+    /// Whether taking `hints` hints keeps a game out of the records, best puzzle results and the
+    /// leaderboard
+    pub fn disqualifies(&self, hints: u32) -> bool {
+        hints > 0 && self.disqualifies_high_score
+    }
+}
+
+impl Default for HintSettings {
+    fn default() -> HintSettings {
+        HintSettings {
+            disqualifies_high_score: true,
+        }
+    }
+}
+
+/// The direction which gives the most points and leaves the most room in the next few moves
+///
+/// New squares can not be predicted, so the lookahead only considers the squares already on the
+/// board.
+pub fn best_direction(board: &Board) -> Option<MovementDirection> {
+    MovementDirection::ALL
+        .iter()
+        .filter_map(|&direction| {
+            evaluate_move(board, direction, HINT_DEPTH).map(|value| (direction, value))
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(direction, _)| direction)
+}
+
+fn evaluate_move(board: &Board, direction: MovementDirection, depth: u32) -> Option<f32> {
+    let mut board = board.clone();
+    let turn = board.slide(direction);
+    if !turn.moved() {
+        return None;
+    }
+
+    let future = if depth > 1 {
+        MovementDirection::ALL
+            .iter()
+            .filter_map(|&direction| evaluate_move(&board, direction, depth - 1))
+            .fold(None, |best: Option<f32>, value| {
+                Some(best.map_or(value, |best| best.max(value)))
+            })
+    } else {
+        None
+    };
+
+    let free_cells = (board.size() * board.size()) as usize - board.tiles().count();
+    let value = future.unwrap_or(free_cells as f32 * EMPTY_CELL_WEIGHT);
+
+    Some(turn.points() as f32 + value)
+}
+
+struct HintArrow {
+    timer: Timer,
+    moves: u32,
+}
+
+fn request_hint(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    grid: Res<Grid>,
+    mut session: ResMut<GameSession>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut arrow_query: Query<With<HintArrow, Entity>>,
+) {
+    if game_state.state != RunningGameState::Running
+        || !keyboard_input.just_pressed(key_bindings.hint)
+    {
+        return;
+    }
+
+    let board = grid.board();
+    let direction = match best_direction(&board) {
+        Some(direction) => direction,
+        None => return,
+    };
+
+    session.stats.hints += 1;
+
+    for entity in &mut arrow_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    for square_move in board.clone().slide(direction).moves {
+        if let Some(square) = grid.get_at(square_move.from) {
+            commands.insert_one(
+                square.entity,
+                animation::Tween::<animation::ScaleLens>::new(1.15, 1., 0.4)
+                    .with_ease(animation::Easing::EaseOutBounce),
+            );
+        }
+    }
+
+    let (x, y) = calculate_grid_position(0, 0);
+    let grid_width = (GRID_SIZE * SQUARE_WIDTH + (GRID_SIZE - 1) * SQUARE_MARGIN) as f32;
+    let angle = match direction {
+        MovementDirection::Up => 0.,
+        MovementDirection::Left => std::f32::consts::FRAC_PI_2,
+        MovementDirection::Down => std::f32::consts::PI,
+        MovementDirection::Right => -std::f32::consts::FRAC_PI_2,
+    };
+    let material = materials.add(Color::rgba(1., 1., 1., 0.8).into());
+    let size = SQUARE_WIDTH as f32 * 0.5;
+
+    commands
+        .spawn((
+            HintArrow {
+                timer: Timer::from_seconds(HINT_DURATION, false),
+                moves: session.stats.moves,
+            },
+            Translation::new(x + grid_width / 2., y + grid_width / 2., 5.),
+            Rotation(Quat::from_rotation_z(angle)),
+            Transform::default(),
+            Scale::default(),
+            animation::Tween::<animation::ScaleLens>::new(0., 1., 0.3)
+                .with_ease(animation::Easing::EaseOutBack),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(primitive(
+                    material.clone(),
+                    &mut meshes,
+                    ShapeType::Triangle(
+                        Vec2::new(-size, 0.),
+                        Vec2::new(size, 0.),
+                        Vec2::new(0., size),
+                    ),
+                    TessellationMode::Fill(&FillOptions::default()),
+                    Vec3::default().into(),
+                ))
+                .with(LocalTransform::default())
+                .with(Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                })
+                .spawn(primitive(
+                    material,
+                    &mut meshes,
+                    ShapeType::Quad(
+                        Vec2::new(-size / 3., -size),
+                        Vec2::new(size / 3., -size),
+                        Vec2::new(size / 3., 0.),
+                        Vec2::new(-size / 3., 0.),
+                    ),
+                    TessellationMode::Fill(&FillOptions::default()),
+                    Vec3::default().into(),
+                ))
+                .with(LocalTransform::default())
+                .with(Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                });
+        });
+}
+
+fn update_hint_arrows(
+    mut commands: Commands,
+    time: Res<Time>,
+    session: Res<GameSession>,
+    mut arrow_query: Query<(Entity, &mut HintArrow)>,
+) {
+    for (entity, mut arrow) in &mut arrow_query.iter() {
+        arrow.timer.tick(time.delta_seconds);
+
+        if arrow.timer.finished || arrow.moves != session.stats.moves {
+            commands.despawn_recursive(entity);
+        }
+    }
+}
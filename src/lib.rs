@@ -0,0 +1,6 @@
+//! The parts of the game that work without bevy, shared by the game and the terminal frontend
+
+pub mod locale;
+pub mod notation;
+pub mod render;
+pub mod rules;
@@ -0,0 +1,240 @@
+//! Translations of the texts shown to the player, one RON file per language
+//!
+//! ```text
+//! (
+//!     name: "Deutsch",
+//!     strings: {
+//!         "score": "Punkte",
+//!         "summary-moves": "{moves} Züge",
+//!     },
+//! )
+//! ```
+//!
+//! The file name is the language code, like `de.ron` or `pt-BR.ron`. Words in braces are replaced
+//! by the arguments of a text. A text missing from a language is taken from its `fallback`
+//! language, then from the language without its region (`pt` for `pt-BR`) and finally from
+//! English. A language may also name a `font` that covers its script.
+
+use ab_glyph::{Font, FontRef};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// The language every text exists in, and the last one in every fallback chain
+pub const DEFAULT_LANGUAGE: &str = "en";
+const LOCALE_EXTENSION: &str = "ron";
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Locale {
+    /// The name of the language in the language itself
+    pub name: String,
+    #[serde(default)]
+    pub fallback: Option<String>,
+    #[serde(default)]
+    pub font: Option<String>,
+    pub strings: HashMap<String, String>,
+}
+
+impl Locale {
+    pub fn parse(source: &str) -> Result<Locale, String> {
+        ron::de::from_str(source).map_err(|err| err.to_string())
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Translations {
+    /// Sorted by language code, which is also the order languages are switched in
+    locales: BTreeMap<String, Locale>,
+}
+
+impl Translations {
+    /// Loads every language in the directory, skipping files that can't be read
+    pub fn load(dir: &Path) -> Translations {
+        let mut translations = Translations::default();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!(
+                    "Could not read translations from {}: {}",
+                    dir.display(),
+                    err
+                );
+                return translations;
+            }
+        };
+
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().map_or(true, |ext| ext != LOCALE_EXTENSION) {
+                continue;
+            }
+
+            let language = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(language) => language.to_string(),
+                None => continue,
+            };
+            match std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| Locale::parse(&source))
+            {
+                Ok(locale) => translations.insert(language, locale),
+                Err(err) => eprintln!("Could not load {}: {}", path.display(), err),
+            }
+        }
+
+        translations
+    }
+
+    pub fn insert(&mut self, language: String, locale: Locale) {
+        self.locales.insert(language, locale);
+    }
+
+    pub fn get(&self, language: &str) -> Option<&Locale> {
+        self.locales.get(language)
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.locales.keys().map(|language| language.as_str())
+    }
+
+    /// The languages a text is looked up in, in order, which don't all need to exist
+    pub fn chain(&self, language: &str) -> Vec<String> {
+        let mut chain: Vec<String> = vec![];
+        let mut next = Some(language.to_string());
+
+        while let Some(language) = next {
+            // A fallback pointing back to an earlier language would never end
+            if chain.contains(&language) {
+                break;
+            }
+
+            next = self
+                .get(&language)
+                .and_then(|locale| locale.fallback.clone())
+                .or_else(|| language.rsplitn(2, '-').nth(1).map(String::from));
+            chain.push(language);
+        }
+
+        if !chain.iter().any(|language| language == DEFAULT_LANGUAGE) {
+            chain.push(DEFAULT_LANGUAGE.to_string());
+        }
+        chain
+    }
+
+    /// The text in the language or its fallbacks with the arguments filled in, or its key
+    pub fn text(&self, language: &str, key: &str, args: &[(&str, String)]) -> String {
+        let template = self
+            .chain(language)
+            .iter()
+            .filter_map(|language| self.get(language))
+            .find_map(|locale| locale.strings.get(key));
+
+        let mut text = match template {
+            Some(template) => template.clone(),
+            None => return key.to_string(),
+        };
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    /// The first font named along the fallback chain of the language
+    pub fn font(&self, language: &str) -> Option<&str> {
+        self.chain(language)
+            .iter()
+            .filter_map(|language| self.get(language))
+            .find_map(|locale| locale.font.as_deref())
+    }
+
+    /// Characters in the texts of the language which the font has no glyphs for
+    pub fn missing_glyphs(&self, language: &str, font_data: &[u8]) -> Result<String, String> {
+        let font = FontRef::try_from_slice(font_data).map_err(|err| err.to_string())?;
+        let mut missing = String::new();
+
+        if let Some(locale) = self.get(language) {
+            for c in locale.strings.values().flat_map(|text| text.chars()) {
+                if !c.is_whitespace() && font.glyph_id(c).0 == 0 && !missing.contains(c) {
+                    missing.push(c);
+                }
+            }
+        }
+        Ok(missing)
+    }
+}
+
+/// The language of the system, like `de-DE` for `LANG=de_DE.UTF-8`
+pub fn system_language() -> Option<String> {
+    let lang = std::env::var("LANG").ok()?;
+    let language = lang.split('.').next()?.replace('_', "-");
+
+    // "C" and "POSIX" only say that there is no language
+    if language.is_empty() || language == "C" || language == "POSIX" {
+        return None;
+    }
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(fallback: Option<&str>, strings: &[(&str, &str)]) -> Locale {
+        Locale {
+            name: String::new(),
+            fallback: fallback.map(String::from),
+            font: None,
+            strings: strings
+                .iter()
+                .map(|&(key, text)| (key.to_string(), text.to_string()))
+                .collect(),
+        }
+    }
+
+    fn translations() -> Translations {
+        let mut translations = Translations::default();
+        translations.insert(
+            "en".into(),
+            locale(None, &[("score", "Score"), ("moves", "{moves} moves")]),
+        );
+        translations.insert("de".into(), locale(None, &[("score", "Punkte")]));
+        translations.insert("de-AT".into(), locale(None, &[]));
+        translations.insert("gsw".into(), locale(Some("de"), &[]));
+        translations
+    }
+
+    #[test]
+    fn locales_are_read_from_ron() {
+        let source = r#"(
+            name: "Deutsch",
+            font: Some("assets/fonts/DejaVuSans.ttf"),
+            strings: { "score": "Punkte" },
+        )"#;
+
+        let mut expected = locale(None, &[("score", "Punkte")]);
+        expected.name = "Deutsch".into();
+        expected.font = Some("assets/fonts/DejaVuSans.ttf".into());
+        assert_eq!(Locale::parse(source), Ok(expected));
+    }
+
+    #[test]
+    fn texts_fall_back_along_the_chain() {
+        let translations = translations();
+
+        assert_eq!(translations.chain("gsw"), vec!["gsw", "de", "en"]);
+        assert_eq!(translations.chain("de-AT"), vec!["de-AT", "de", "en"]);
+        assert_eq!(translations.text("gsw", "score", &[]), "Punkte");
+        assert_eq!(translations.text("de-AT", "score", &[]), "Punkte");
+        assert_eq!(translations.text("fr", "score", &[]), "Score");
+        assert_eq!(translations.text("de", "missing", &[]), "missing");
+    }
+
+    #[test]
+    fn arguments_are_filled_in() {
+        let translations = translations();
+
+        assert_eq!(
+            translations.text("de", "moves", &[("moves", "12".to_string())]),
+            "12 moves"
+        );
+    }
+}
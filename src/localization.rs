@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy_squares::locale::{self, Translations, DEFAULT_LANGUAGE};
+use std::collections::HashMap;
+use std::path::Path;
+
+const LOCALE_DIR: &str = "assets/locales";
+pub const DEFAULT_FONT: &str = "assets/bungee_inline_regular.ttf";
+/// Also covers Greek and Cyrillic, for languages the default font has no glyphs for
+const FALLBACK_FONT: &str = "assets/fonts/DejaVuSans.ttf";
+
+/// Shows every `LocalizedText` in the chosen language, F2 switches to the next language
+#[derive(Default)]
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Texts are changed before POST_UPDATE, where their size is laid out
+        app.add_system(switch_language.system())
+            .add_system(update_localized_text.system());
+    }
+}
+
+pub struct Localization {
+    pub language: String,
+    translations: Translations,
+    /// The font of every language, checked when loading to have glyphs for all of its texts
+    fonts: HashMap<String, String>,
+    /// The font of the current language, loaded once it is first needed
+    font: Option<Handle<Font>>,
+}
+
+impl Localization {
+    /// Loads all languages, starting with the given one or else the language of the system
+    pub fn load(language: Option<String>) -> Localization {
+        let translations = Translations::load(Path::new(LOCALE_DIR));
+        let fonts = translations
+            .languages()
+            .map(|language| (language.to_string(), choose_font(&translations, language)))
+            .collect();
+
+        Localization {
+            language: resolve_language(language),
+            translations,
+            fonts,
+            font: None,
+        }
+    }
+
+    pub fn text(&self, key: &str) -> String {
+        self.translations.text(&self.language, key, &[])
+    }
+
+    pub fn localize(&self, text: &LocalizedText) -> String {
+        if text.key.is_empty() {
+            return text
+                .args
+                .first()
+                .map_or_else(String::new, |(_, value)| value.clone());
+        }
+
+        let args: Vec<(&str, String)> = text
+            .args
+            .iter()
+            .map(|(name, value)| (*name, value.clone()))
+            .collect();
+        self.translations.text(&self.language, text.key, &args)
+    }
+
+    /// The font of the first language along the fallback chain that has one
+    fn font_path(&self) -> &str {
+        self.translations
+            .chain(&self.language)
+            .iter()
+            .find_map(|language| self.fonts.get(language))
+            .map_or(DEFAULT_FONT, |font| font.as_str())
+    }
+
+    /// The codes of all languages, in order
+    pub fn languages(&self) -> Vec<String> {
+        self.translations
+            .languages()
+            .map(|language| language.to_string())
+            .collect()
+    }
+
+    /// Switches to the given language, or else the language of the system
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.language = resolve_language(language);
+        self.font = None;
+    }
+
+    /// Switches to the next language, in the order of their codes
+    pub fn next_language(&mut self) {
+        let languages: Vec<&str> = self.translations.languages().collect();
+        if languages.is_empty() {
+            return;
+        }
+
+        // The language may only exist through its fallbacks, like `de-DE` through `de`
+        let current = self
+            .translations
+            .chain(&self.language)
+            .iter()
+            .find_map(|language| languages.iter().position(|known| known == language));
+        let next = current.map_or(0, |index| (index + 1) % languages.len());
+
+        self.language = languages[next].to_string();
+        self.font = None;
+    }
+}
+
+fn resolve_language(language: Option<String>) -> String {
+    language
+        .or_else(locale::system_language)
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+/// The font the language asks for, or the fallback font if that can't show all of its texts
+fn choose_font(translations: &Translations, language: &str) -> String {
+    let font = translations.font(language).unwrap_or(DEFAULT_FONT);
+    let missing = std::fs::read(font)
+        .map_err(|err| err.to_string())
+        .and_then(|data| translations.missing_glyphs(language, &data));
+
+    match missing {
+        Ok(missing) if missing.is_empty() => font.to_string(),
+        Ok(missing) => {
+            eprintln!(
+                "{} has no glyphs for `{}` used by {}, using {} instead",
+                font, missing, language, FALLBACK_FONT
+            );
+            FALLBACK_FONT.to_string()
+        }
+        Err(err) => {
+            eprintln!("Could not check the font {}: {}", font, err);
+            font.to_string()
+        }
+    }
+}
+
+/// A text shown in the language of the player, which follows when the language changes
+///
+/// Spawned texts only need this component, their value and font are filled in afterwards.
+#[derive(Clone, Debug)]
+pub struct LocalizedText {
+    /// Texts without a key show their first argument untranslated
+    pub key: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl LocalizedText {
+    pub fn new(key: &'static str) -> LocalizedText {
+        LocalizedText { key, args: vec![] }
+    }
+
+    /// Shows nothing, for texts that are only visible some of the time
+    pub fn empty() -> LocalizedText {
+        LocalizedText::new("")
+    }
+
+    /// Shows the text as it is in every language, like the name of a key
+    pub fn verbatim(text: impl ToString) -> LocalizedText {
+        LocalizedText::empty().with_arg("", text)
+    }
+
+    pub fn with_arg(mut self, name: &'static str, value: impl ToString) -> LocalizedText {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+fn switch_language(keyboard_input: Res<Input<KeyCode>>, mut localization: ResMut<Localization>) {
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        localization.next_language();
+    }
+}
+
+fn update_localized_text(
+    mut localization: ResMut<Localization>,
+    asset_server: Res<AssetServer>,
+    mut text_query: Query<(&LocalizedText, &mut Text)>,
+) {
+    let font = match &localization.font {
+        Some(font) => font.clone(),
+        None => {
+            let font: Handle<Font> = asset_server.load(localization.font_path()).unwrap();
+            localization.font = Some(font.clone());
+            font
+        }
+    };
+
+    for (localized, mut text) in &mut text_query.iter() {
+        let value = localization.localize(&localized);
+
+        // Only touch the text when it changes, as every change lays out the glyphs again
+        if text.value != value {
+            text.value = value;
+        }
+        if text.font != font {
+            text.font = font.clone();
+        }
+    }
+}
@@ -1,75 +1,263 @@
+use animation::TweenCommands;
 use bevy::render::pass::ClearColor;
+use bevy::window::WindowMode;
 use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
     prelude::*,
 };
 use bevy_prototype_lyon::prelude::*;
-use rand::{prelude::SliceRandom, thread_rng};
+use bevy_squares::notation::Position;
+use bevy_squares::render::Palette;
+use bevy_squares::rules;
+use localization::{Localization, LocalizedText};
+use rand::{thread_rng, Rng, SeedableRng};
+use rules::{
+    Board, Combo, MovementDirection, SpawnPolicy, SpawnRng, Spawner, StartingPosition, Tile,
+    TileKind,
+};
+use settings::{KeyBindings, Settings};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use theme::{Theme, Themed};
 
+mod accessibility;
+mod ai;
 mod animation;
+mod cli;
+#[cfg(debug_assertions)]
+mod consistency;
+mod copy_paste;
+mod daily;
+mod debug;
+mod effects;
+mod hint;
+mod localization;
+mod menu;
+mod puzzle;
+mod replay;
+mod settings;
+mod snapshot;
+mod storage;
+mod summary;
+mod text_sprite;
+mod theme;
+
+#[cfg(test)]
+mod tests;
 
 const UI_OFFSET: u32 = 100;
 const WINDOW_WIDTH: u32 = 600;
 const WINDOW_HEIGHT: u32 = WINDOW_WIDTH + UI_OFFSET;
 const PADDING: u32 = 25;
 
-const GRID_SIZE: u32 = 4;
+const GRID_SIZE: u32 = rules::BOARD_SIZE;
 const SQUARE_WIDTH: u32 = 125;
 const SQUARE_MARGIN: u32 =
     ((WINDOW_WIDTH - 2 * PADDING) - GRID_SIZE * SQUARE_WIDTH) / (GRID_SIZE - 1);
 const TIME_TO_DIE: f32 = 0.35;
+const SLIDE_DURATION: f32 = 0.15;
+const POP_DURATION: f32 = 0.1;
+/// How long squares fade in or out with reduced motion
+const FADE_DURATION: f32 = 0.25;
 
 fn main() {
     env_logger::init();
+
+    // The command line overrides the saved settings for this launch
+    let settings = Settings::load();
+    let args = std::env::args().skip(1);
+    let options = match cli::Options::parse_with(settings.launch_options(), args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    // The settings screen shows what the game follows, but only saves what was changed on it
+    let settings = settings.with_options(&options);
+
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    if options.validate_levels {
+        let all_solvable = puzzle::validate_levels();
+        std::process::exit(if all_solvable { 0 } else { 1 });
+    }
+
+    let replay_file = options.replay.as_ref().map(|path| {
+        replay::ReplayFile::load(path).unwrap_or_else(|err| {
+            eprintln!("Could not load the replay {}: {}", path.display(), err);
+            std::process::exit(1);
+        })
+    });
+    if let (Some(replay), Some(path)) = (&replay_file, &options.export_gif) {
+        let style = options.theme.render_style(options.palette);
+        if let Err(err) = snapshot::export_replay(replay, style, path) {
+            eprintln!("Could not export the replay to {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // A replay has to be played in the mode it was recorded in
+    let game_mode = replay_file
+        .as_ref()
+        .map(|replay| replay.mode)
+        .unwrap_or(options.mode);
+    let rng = match (&replay_file, game_mode) {
+        (Some(replay), _) => GameRng::new(Some(replay.seed)),
+        (None, GameMode::Daily) => GameRng::daily(),
+        (None, _) => GameRng::new(options.seed),
+    };
+    let (mut starting_position, initial_state) = match game_mode {
+        GameMode::Puzzle => (StartingPosition::empty(), RunningGameState::LevelSelect),
+        GameMode::Arcade => (StartingPosition::arcade(), RunningGameState::Running),
+        _ => (StartingPosition::classic(), RunningGameState::Running),
+    };
+
+    if let Some(path) = &options.load {
+        starting_position.board = puzzle::load_board(path).unwrap_or_else(|err| {
+            eprintln!("Could not load the board {}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+    }
+
+    let mut session = GameSession::new(rng, &starting_position);
+    session.replay = replay_file.map(|replay| replay::Replay::new(replay.moves));
+    session.custom_start = options.load.is_some();
+    let localization = Localization::load(options.language);
+
     App::build()
         .add_resource(WindowDescriptor {
             width: WINDOW_WIDTH,
             height: WINDOW_HEIGHT,
-            title: String::from("Squares - Bevy Edition"),
-            vsync: true,
+            title: localization.text("window-title"),
+            vsync: settings.vsync,
             resizable: false,
+            mode: if settings.fullscreen {
+                WindowMode::BorderlessFullscreen
+            } else {
+                WindowMode::Windowed
+            },
             ..Default::default()
         })
-        .add_resource(Msaa { samples: 1 })
-        .add_resource(ClearColor(Color::rgb(
-            255. / 255.,
-            211. / 255.,
-            182. / 255.,
-        )))
+        .add_resource(Msaa {
+            samples: settings.msaa_samples,
+        })
+        .add_resource(ClearColor(options.theme.background()))
         .add_resource(GameState {
-            state: RunningGameState::Running,
+            state: initial_state,
             event_reader: Default::default(),
+            restart_reader: Default::default(),
+            move_reader: Default::default(),
         })
-        .add_event::<ScoreChange>()
-        .add_event::<RunningGameState>()
-        .init_resource::<ScoreState>()
+        .add_resource(game_mode)
+        .add_resource(session)
+        .add_resource(starting_position)
+        .add_resource(localization)
         .add_default_plugins()
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(animation::AnimationPlugin)
+        .add_plugin(GameplayPlugin)
+        .add_resource(settings.key_bindings)
+        .add_resource(options.theme)
+        .add_resource(animation::AnimationSettings {
+            time_scale: options.animation_speed,
+            reduced_motion: options.reduced_motion,
+        })
+        .add_plugin(accessibility::AccessibilityPlugin)
+        .add_resource(accessibility::Accessibility {
+            palette: options.palette,
+            tile_numbers: options.tile_numbers,
+            text_scale: options.text_scale,
+        })
+        .add_plugin(ai::AiPlugin)
+        .add_resource(ai::AutoPlayer::new(options.ai))
+        .add_plugin(copy_paste::CopyPastePlugin)
+        .add_plugin(daily::DailyPlugin)
+        .add_plugin(debug::DebugPlugin)
+        .add_plugin(effects::EffectsPlugin)
+        .add_resource(settings.effects)
+        .add_plugin(hint::HintPlugin)
+        .add_plugin(localization::LocalizationPlugin)
+        .add_plugin(menu::MenuPlugin)
+        .add_plugin(puzzle::PuzzlePlugin)
+        .add_plugin(settings::SettingsPlugin)
+        .add_resource(settings)
+        .add_plugin(snapshot::SnapshotPlugin)
+        .add_plugin(summary::SummaryPlugin)
+        .add_plugin(text_sprite::TextSpritePlugin)
         .add_startup_system(setup.system())
-        .add_system_to_stage(
-            bevy::app::stage::PRE_UPDATE,
-            handle_game_state_updates.system(),
-        )
-        .add_system(move_squares.system())
-        .add_system(update_score_text.system())
+        .add_system(update_score_target.system())
         .add_system_to_stage(bevy::app::stage::POST_UPDATE, fps_update_system.system())
         .add_system_to_stage(bevy::app::stage::POST_UPDATE, update_colors.system())
-        .add_system_to_stage(bevy::app::stage::POST_UPDATE, kill_after_update.system())
-        .add_system_to_stage(
-            bevy::app::stage::POST_UPDATE,
-            sync_square_grid_position.system(),
-        )
-        .add_system_to_stage(bevy::app::stage::POST_UPDATE, handle_game_restart.system())
         .run();
 }
 
+/// Playing the game itself: moving squares, scoring, restarting and animating the board
+///
+/// Nothing in here needs a window or a renderer, only assets and input, so tests can run it
+/// headlessly. The resources describing the game (`GameState`, `GameSession`, `StartingPosition`,
+/// `Grid` and `SquareColors`) have to be added separately, a `Theme` or `Accessibility` only to
+/// replace the default.
+struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ScoreChange>()
+            .add_event::<RunningGameState>()
+            .add_event::<RestartGame>()
+            .add_event::<SquareEvent>()
+            .add_event::<MoveRequest>()
+            .add_event::<LoadPosition>()
+            .init_resource::<ScoreState>()
+            .init_resource::<PositionLoader>()
+            .init_resource::<ScoreTarget>()
+            .init_resource::<FadeMaterials>()
+            .init_resource::<Theme>()
+            .init_resource::<accessibility::Accessibility>()
+            .init_resource::<KeyBindings>()
+            .add_plugin(animation::AnimationPlugin)
+            .add_plugin(replay::ReplayPlugin)
+            .add_system_to_stage(
+                bevy::app::stage::PRE_UPDATE,
+                handle_game_state_updates.system(),
+            )
+            .add_system_to_stage(bevy::app::stage::PRE_UPDATE, read_move_input.system())
+            .add_system(load_position.system())
+            .add_system(move_squares.system())
+            .add_system(update_score_text.system())
+            .add_system(update_combo_text.system())
+            .add_system(animate_removals.system())
+            .add_system(finish_merges.system())
+            .add_system_to_stage(
+                bevy::app::stage::POST_UPDATE,
+                sync_square_grid_position.system(),
+            )
+            .add_system_to_stage(
+                bevy::app::stage::POST_UPDATE,
+                restart_button_system.system(),
+            )
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, handle_game_restart.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, fade_squares.system())
+            .add_system_to_stage(bevy::app::stage::LAST, finish_loading_position.system())
+            .add_system_to_stage(bevy::app::stage::LAST, remove_fade_materials.system());
+
+        #[cfg(debug_assertions)]
+        app.add_plugin(consistency::ConsistencyPlugin);
+    }
+}
+
 fn setup(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
     asset_server: Res<AssetServer>,
+    starting_position: Res<StartingPosition>,
+    theme: Res<Theme>,
+    accessibility: Res<accessibility::Accessibility>,
 ) {
     let font_handle = asset_server
         .load("assets/bungee_inline_regular.ttf")
@@ -77,6 +265,7 @@ fn setup(
 
     commands
         .spawn(Camera2dComponents::default())
+        .with(MainCamera)
         .spawn(UiCameraComponents::default());
 
     let mut grid = Grid::new();
@@ -95,7 +284,7 @@ fn setup(
                 .with_children(|parent| {
                     parent
                         .spawn(SpriteComponents {
-                            material: materials.add(Color::rgba(0., 0., 0., 0.2).into()),
+                            material: materials.add(theme.cell().into()),
                             draw: Draw {
                                 is_transparent: true,
                                 ..Default::default()
@@ -115,29 +304,20 @@ fn setup(
                             scale: Scale(1.05),
                             ..Default::default()
                         })
-                        .with(LocalTransform::default());
+                        .with(LocalTransform::default())
+                        .with(Themed::Cell);
                 });
         }
     }
 
-    let square_colors = SquareColors::new(&mut materials);
+    let square_colors = SquareColors::new(&mut materials, accessibility.palette);
 
-    spawn_square(
-        &mut commands,
-        &mut grid,
-        &mut meshes,
-        &square_colors,
-        (1, 2),
-        1,
-        None,
-    );
-    spawn_square(
+    spawn_board(
         &mut commands,
         &mut grid,
         &mut meshes,
         &square_colors,
-        (2, 1),
-        2,
+        &starting_position.board,
         None,
     );
 
@@ -168,16 +348,18 @@ fn setup(
                         ..Default::default()
                     },
                     text: Text {
-                        value: "Score".to_string(),
+                        value: String::new(),
                         font: font_handle.clone(),
                         style: TextStyle {
                             font_size: 20.0,
-                            color: Color::rgb(204. / 255., 112. / 255., 119. / 255.),
+                            color: theme.accent(),
                         },
                         ..Default::default()
                     },
                     ..Default::default()
                 })
+                .with(LocalizedText::new("score"))
+                .with(Themed::Accent)
                 .spawn(TextComponents {
                     style: Style {
                         margin: Rect::all(Val::Px(5.0)),
@@ -188,14 +370,39 @@ fn setup(
                         font: font_handle.clone(),
                         style: TextStyle {
                             font_size: 40.0,
-                            color: Color::rgb(76. / 255., 42. / 255., 44. / 255.),
+                            color: theme.text(),
                         },
                         ..Default::default()
                     },
                     ..Default::default()
                 })
-                .with(ScoreText);
+                .with(ScoreText)
+                .with(Themed::Text);
+        })
+        .spawn(TextComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(20.),
+                    top: Val::Px(40.),
+                    ..Default::default()
+                },
+                align_self: AlignSelf::FlexEnd,
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: font_handle.clone(),
+                style: TextStyle {
+                    font_size: COMBO_FONT_SIZE,
+                    color: theme.accent(),
+                },
+            },
+            ..Default::default()
         })
+        .with(ComboText::default())
+        .with(Themed::Accent)
+        .with(LocalizedText::empty())
         .spawn(TextComponents {
             style: Style {
                 position_type: PositionType::Absolute,
@@ -208,22 +415,196 @@ fn setup(
                 ..Default::default()
             },
             text: Text {
-                value: "FPS:".to_string(),
+                value: String::new(),
                 font: font_handle,
                 style: TextStyle {
                     font_size: 12.0,
-                    color: Color::BLACK,
+                    color: theme.text(),
                 },
             },
             ..Default::default()
         })
-        .with(FPS);
+        .with(FPS)
+        .with(Themed::Text)
+        .with(LocalizedText::new("fps").with_arg("fps", ""));
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum GameMode {
+    Classic,
+    Daily,
+    Puzzle,
+    /// Classic with special tiles
+    Arcade,
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GameMode, String> {
+        match s {
+            "classic" => Ok(GameMode::Classic),
+            "daily" => Ok(GameMode::Daily),
+            "puzzle" => Ok(GameMode::Puzzle),
+            "arcade" => Ok(GameMode::Arcade),
+            _ => Err(format!(
+                "unknown mode `{}`, expected classic, daily, arcade or puzzle",
+                s
+            )),
+        }
+    }
+}
+
+/// Written as accepted by `--mode`
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            GameMode::Classic => "classic",
+            GameMode::Daily => "daily",
+            GameMode::Puzzle => "puzzle",
+            GameMode::Arcade => "arcade",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The single source of randomness for gameplay, so that a seed fully determines a game
+struct GameRng {
+    fixed_seed: Option<u64>,
+    /// The day of the current daily challenge, every game takes the seed of the day it starts on
+    daily: Option<daily::Date>,
+    /// The seed of the current game
+    seed: u64,
+    rng: SpawnRng,
+}
+
+impl GameRng {
+    /// Uses the given seed for every game, or a fresh random one if there is none
+    fn new(fixed_seed: Option<u64>) -> GameRng {
+        let seed = fixed_seed.unwrap_or_else(|| thread_rng().gen());
+        GameRng {
+            fixed_seed,
+            daily: None,
+            seed,
+            rng: SpawnRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Seeds every game with the daily challenge of the day it starts on
+    fn daily() -> GameRng {
+        let date = daily::Date::today();
+        GameRng {
+            daily: Some(date),
+            ..GameRng::new(Some(date.seed()))
+        }
+    }
+
+    fn restart(&mut self) {
+        *self = match self.daily {
+            Some(_) => GameRng::daily(),
+            None => GameRng::new(self.fixed_seed),
+        };
+    }
+
+    /// Starts over with the seed of the current game, to play it again
+    fn repeat(&mut self) {
+        self.rng = SpawnRng::seed_from_u64(self.seed);
+    }
+}
+
+#[derive(Default)]
+struct GameStats {
+    moves: u32,
+    hints: u32,
+    /// Seconds spent in the running game
+    play_time: f32,
+    /// The board or the spawns were changed in the debug editor
+    edited: bool,
+}
+
+/// Everything that changes during a single game and gets reset on restart
+struct GameSession {
+    rng: GameRng,
+    spawner: Spawner,
+    combo: Combo,
+    stats: GameStats,
+    /// Every move that changed the board, which together with the seed replays the game
+    history: Vec<MovementDirection>,
+    /// Plays the moves of the previous game instead of taking input
+    replay: Option<replay::Replay>,
+    queued_replay: Option<replay::Replay>,
+    /// The games start from a loaded or pasted board instead of the usual one of their mode,
+    /// which stays so across restarts until a mode is picked in the main menu
+    custom_start: bool,
 }
 
-#[derive(PartialEq, Clone, Copy)]
+impl GameSession {
+    fn new(rng: GameRng, starting_position: &StartingPosition) -> GameSession {
+        GameSession {
+            rng,
+            spawner: Spawner::new(starting_position.spawn_policy.clone()),
+            combo: Combo::new(starting_position.combo_rules),
+            stats: GameStats::default(),
+            history: Vec::new(),
+            replay: None,
+            queued_replay: None,
+            custom_start: false,
+        }
+    }
+
+    /// Whether the game played out from the usual board of its mode on its own, so that it can
+    /// set records and be replayed from its seed
+    ///
+    /// A replay only repeats a game that already counted.
+    fn is_comparable(&self) -> bool {
+        self.replay.is_none() && !self.custom_start && !self.stats.edited
+    }
+
+    /// Makes the next restart replay the current game
+    fn queue_replay(&mut self) {
+        self.queued_replay = Some(replay::Replay::new(self.history.clone()));
+    }
+
+    fn restart(&mut self, starting_position: &StartingPosition) {
+        self.replay = self.queued_replay.take();
+        if self.replay.is_some() {
+            self.rng.repeat();
+        } else {
+            self.rng.restart();
+        }
+
+        self.spawner = Spawner::new(starting_position.spawn_policy.clone());
+        self.combo = Combo::new(starting_position.combo_rules);
+        self.stats = GameStats::default();
+        self.history.clear();
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
 enum RunningGameState {
     Running,
     GameOver,
+    LevelSelect,
+    Solved,
+    MainMenu,
+    /// The settings screen is open, the game waits behind it
+    Settings,
+}
+
+struct RestartGame;
+
+/// Asks to move all squares, sent for key presses or by a replay
+#[derive(Clone, Copy)]
+struct MoveRequest(MovementDirection);
+
+/// Replaces the board, score and moves, for example with a position pasted from the clipboard
+struct LoadPosition(Position);
+
+/// The score and moves of a loaded position, which are set once the game has restarted
+#[derive(Default)]
+struct PositionLoader {
+    load_reader: EventReader<LoadPosition>,
+    pending: Option<(u64, u32)>,
 }
 
 struct RestartButton;
@@ -232,147 +613,166 @@ struct GameOverScreen;
 struct GameState {
     state: RunningGameState,
     event_reader: EventReader<RunningGameState>,
+    restart_reader: EventReader<RestartGame>,
+    move_reader: EventReader<MoveRequest>,
 }
 
 fn handle_game_state_updates(
-    mut commands: Commands,
     mut game_state: ResMut<GameState>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    asset_server: Res<AssetServer>,
     game_events: Res<Events<RunningGameState>>,
 ) {
     for event in game_state.event_reader.iter(&game_events) {
-        match event {
-            RunningGameState::GameOver => {
-                let font_handle = asset_server
-                    .load("assets/bungee_inline_regular.ttf")
-                    .unwrap();
-                commands
-                    .spawn(NodeComponents {
-                        style: Style {
-                            position_type: PositionType::Absolute,
-                            position: Rect::all(Val::Percent(25.)),
-                            align_items: AlignItems::Center,
-                            justify_content: JustifyContent::Center,
-                            flex_direction: FlexDirection::Column,
-                            ..Default::default()
-                        },
-                        material: materials.add(Color::NONE.into()),
-                        ..Default::default()
-                    })
-                    .with(GameOverScreen)
-                    .with_children(|parent| {
-                        parent
-                            .spawn(ButtonComponents {
-                                style: Style {
-                                    margin: Rect {
-                                        top: Val::Px(10.0),
-                                        ..Default::default()
-                                    },
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    padding: Rect::all(Val::Px(15.)),
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            })
-                            .with(RestartButton)
-                            .with_children(|parent| {
-                                parent.spawn(TextComponents {
-                                    text: Text {
-                                        value: "Restart".to_string(),
-                                        font: font_handle.clone(),
-                                        style: TextStyle {
-                                            font_size: 25.,
-                                            color: Color::rgb(0., 0., 0.),
-                                        },
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                });
-                            })
-                            .spawn(TextComponents {
-                                style: Style {
-                                    margin: Rect::all(Val::Px(5.0)),
-                                    ..Default::default()
-                                },
-                                text: Text {
-                                    value: "GAME OVER".to_string(),
-                                    font: font_handle.clone(),
-                                    style: TextStyle {
-                                        font_size: 40.0,
-                                        color: Color::rgb(0., 0., 0.),
-                                    },
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            });
-                    });
-            }
+        game_state.state = *event;
+    }
+}
+
+fn read_move_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    session: Res<GameSession>,
+    mut move_requests: ResMut<Events<MoveRequest>>,
+) {
+    // A replay makes its own moves until it runs out
+    if session
+        .replay
+        .as_ref()
+        .map_or(false, |replay| !replay.is_finished())
+    {
+        return;
+    }
+
+    let direction = if keyboard_input.just_pressed(key_bindings.up) {
+        MovementDirection::Up
+    } else if keyboard_input.just_pressed(key_bindings.down) {
+        MovementDirection::Down
+    } else if keyboard_input.just_pressed(key_bindings.right) {
+        MovementDirection::Right
+    } else if keyboard_input.just_pressed(key_bindings.left) {
+        MovementDirection::Left
+    } else {
+        return;
+    };
+
+    move_requests.send(MoveRequest(direction));
+}
+
+fn restart_button_system(
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut button_query: Query<With<RestartButton, &Interaction>>,
+) {
+    for interaction in &mut button_query.iter() {
+        match *interaction {
+            Interaction::Clicked => restart_events.send(RestartGame),
             _ => (),
         }
-        game_state.state = *event;
     }
 }
 
 fn handle_game_restart(
     mut commands: Commands,
     square_colors: Res<SquareColors>,
+    starting_position: Res<StartingPosition>,
+    mut game_state: ResMut<GameState>,
+    mut session: ResMut<GameSession>,
     mut grid: ResMut<Grid>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut score_events: ResMut<Events<ScoreChange>>,
     mut game_events: ResMut<Events<RunningGameState>>,
-    mut button_query: Query<With<RestartButton, &Interaction>>,
+    restart_events: Res<Events<RestartGame>>,
     mut scene_query: Query<With<GameOverScreen, Entity>>,
     mut square_query: Query<With<GameSquare, Entity>>,
 ) {
-    for interaction in &mut button_query.iter() {
-        match *interaction {
-            Interaction::Clicked => {
-                score_events.send(ScoreChange::Reset);
-                game_events.send(RunningGameState::Running);
+    if game_state
+        .restart_reader
+        .iter(&restart_events)
+        .next()
+        .is_none()
+    {
+        return;
+    }
 
-                for scene_entity in &mut scene_query.iter() {
-                    commands.despawn_recursive(scene_entity);
-                }
+    score_events.send(ScoreChange::Reset);
+    game_events.send(RunningGameState::Running);
 
-                for square_entity in &mut square_query.iter() {
-                    commands.despawn_recursive(square_entity);
-                }
+    for scene_entity in &mut scene_query.iter() {
+        commands.despawn_recursive(scene_entity);
+    }
 
-                grid.clear();
+    for square_entity in &mut square_query.iter() {
+        commands.despawn_recursive(square_entity);
+    }
 
-                spawn_square(
-                    &mut commands,
-                    &mut grid,
-                    &mut meshes,
-                    &square_colors,
-                    (1, 2),
-                    1,
-                    Some(MovementDirection::Up),
-                );
-                spawn_square(
-                    &mut commands,
-                    &mut grid,
-                    &mut meshes,
-                    &square_colors,
-                    (2, 1),
-                    2,
-                    Some(MovementDirection::Down),
-                );
-            }
-            _ => (),
-        }
+    grid.clear();
+    session.restart(&starting_position);
+
+    spawn_board(
+        &mut commands,
+        &mut grid,
+        &mut meshes,
+        &square_colors,
+        &starting_position.board,
+        Some(MovementDirection::Down),
+    );
+}
+
+/// Restarts the game from the loaded position, which restarts and replays then go back to
+fn load_position(
+    mut loader: ResMut<PositionLoader>,
+    mut starting_position: ResMut<StartingPosition>,
+    mut session: ResMut<GameSession>,
+    load_events: Res<Events<LoadPosition>>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+) {
+    let position = match loader.load_reader.latest(&load_events) {
+        Some(LoadPosition(position)) => position.clone(),
+        None => return,
+    };
+
+    if position.board.size() != GRID_SIZE {
+        eprintln!(
+            "Could not load the position, only {0}x{0} boards can be played",
+            GRID_SIZE
+        );
+        return;
+    }
+
+    starting_position.board = position.board;
+    // Like `--seed`, a known seed is kept for all following games
+    if let Some(seed) = position.seed {
+        session.rng = GameRng::new(Some(seed));
+    }
+    session.queued_replay = None;
+    session.custom_start = true;
+    loader.pending = Some((position.score, position.moves));
+    restart_events.send(RestartGame);
+}
+
+fn finish_loading_position(
+    mut loader: ResMut<PositionLoader>,
+    mut session: ResMut<GameSession>,
+    mut score_events: ResMut<Events<ScoreChange>>,
+) {
+    if let Some((score, moves)) = loader.pending.take() {
+        score_events.send(ScoreChange::Set(score));
+        session.stats.moves = moves;
     }
 }
 
 struct FPS;
 
-fn fps_update_system(diagnostics: Res<Diagnostics>, mut query: Query<With<FPS, &mut Text>>) {
+fn fps_update_system(
+    diagnostics: Res<Diagnostics>,
+    settings: Res<Settings>,
+    mut query: Query<With<FPS, &mut LocalizedText>>,
+) {
     for mut text in &mut query.iter() {
+        if !settings.show_fps {
+            *text = LocalizedText::empty();
+            continue;
+        }
         if let Some(fps) = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS) {
             if let Some(average) = fps.average() {
-                text.value = format!("FPS: {:.2}", average);
+                *text = LocalizedText::new("fps").with_arg("fps", format!("{:.2}", average));
             }
         }
     }
@@ -390,11 +790,20 @@ fn calculate_grid_position(x: i32, y: i32) -> (f32, f32) {
 }
 
 const SQUARE_OUTLINE_COLOR: u64 = 3;
+const SQUARE_WALL_COLOR: u64 = 5;
+const SQUARE_MARKER_COLOR: u64 = 6;
+const SQUARE_HIDDEN_COLOR: u64 = 7;
 
-struct SquareColors(HashMap<u64, Handle<ColorMaterial>>);
+/// Squares up to this score get their own color
+const COLORED_SCORES: u32 = 14;
+
+struct SquareColors {
+    materials: HashMap<u64, Handle<ColorMaterial>>,
+    palette: Palette,
+}
 
 impl SquareColors {
-    fn new(materials: &mut Assets<ColorMaterial>) -> SquareColors {
+    fn new(materials: &mut Assets<ColorMaterial>, palette: Palette) -> SquareColors {
         let mut map = HashMap::new();
 
         map.insert(0, materials.add(Color::rgb(1., 1., 1.).into()));
@@ -402,77 +811,302 @@ impl SquareColors {
             SQUARE_OUTLINE_COLOR,
             materials.add(Color::rgba(1., 1., 1., 0.4).into()),
         );
-        for (i, score) in [
-            1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096, 8172,
-        ]
-        .iter()
-        .enumerate()
-        {
-            map.insert(*score, {
-                let rgb = bracket_color::prelude::HSV::from_f32(i as f32 / 14., 0.8, 0.90).to_rgb();
-
-                materials.add(Color::rgb(rgb.r, rgb.g, rgb.b).into())
-            });
+        map.insert(
+            SQUARE_WALL_COLOR,
+            materials.add(Color::rgb(0.35, 0.3, 0.3).into()),
+        );
+        map.insert(
+            SQUARE_MARKER_COLOR,
+            materials.add(Color::rgba(0., 0., 0., 0.4).into()),
+        );
+        map.insert(SQUARE_HIDDEN_COLOR, materials.add(Color::NONE.into()));
+        for power in 0..COLORED_SCORES {
+            let score = 1 << power;
+            let color = theme::to_color(palette.color(Tile::normal(score)));
+            map.insert(score, materials.add(color.into()));
         }
 
-        SquareColors(map)
+        SquareColors {
+            materials: map,
+            palette,
+        }
+    }
+
+    /// Recolors the squares in place, so that squares on the board change as well
+    fn set_palette(&mut self, palette: Palette, materials: &mut Assets<ColorMaterial>) {
+        for power in 0..COLORED_SCORES {
+            let score = 1 << power;
+            if let Some(material) = materials.get_mut(&self.get(score)) {
+                material.color = theme::to_color(palette.color(Tile::normal(score)));
+            }
+        }
+        self.palette = palette;
     }
 
     fn get(&self, score: u64) -> Handle<ColorMaterial> {
-        self.0
+        self.materials
             .get(&score)
-            .unwrap_or_else(|| self.0.get(&0).unwrap())
+            .unwrap_or_else(|| self.materials.get(&0).unwrap())
             .clone()
     }
+
+    fn tile_color(&self, tile: Tile) -> Handle<ColorMaterial> {
+        match tile.kind {
+            TileKind::Wall => self.get(SQUARE_WALL_COLOR),
+            _ => self.get(tile.score),
+        }
+    }
+
+    fn marker_color(&self, tile: Tile) -> Handle<ColorMaterial> {
+        match tile.kind {
+            TileKind::Normal | TileKind::Wall => self.get(SQUARE_HIDDEN_COLOR),
+            _ => self.get(SQUARE_MARKER_COLOR),
+        }
+    }
 }
 
 enum ScoreChange {
+    /// Points made by a merge, shown once the merged square reaches the score
     Add(u64),
+    /// Replaces the score right away, like for a loaded position
+    Set(u64),
     Reset,
 }
 
+/// What happened to squares during a move, for effects to react to
+enum SquareEvent {
+    /// `entity` slid onto the `merged` square at `position` and became `tile`
+    Merged {
+        entity: Entity,
+        merged: Entity,
+        position: (u32, u32),
+        tile: Tile,
+        points: u64,
+    },
+    Spawned {
+        entity: Entity,
+    },
+    /// `entity` was removed from the board by a bomb
+    Cleared {
+        entity: Entity,
+    },
+}
+
 #[derive(Default)]
 struct ScoreState {
     score: u64,
+    /// What the score text counts up to, lagging behind while merged squares fly to it
+    shown: u64,
     event_reader: EventReader<ScoreChange>,
 }
 
 struct ScoreText;
 
+const COMBO_FONT_SIZE: f32 = 20.0;
+
+/// Shows the current combo multiplier, if there is one
+#[derive(Default)]
+struct ComboText {
+    multiplier: u64,
+}
+
+fn update_combo_text(
+    mut commands: Commands,
+    session: Res<GameSession>,
+    accessibility: Res<accessibility::Accessibility>,
+    mut combo_query: Query<(&mut ComboText, &mut LocalizedText, Entity)>,
+) {
+    let multiplier = session.combo.multiplier();
+
+    for (mut combo_text, mut text, entity) in &mut combo_query.iter() {
+        if combo_text.multiplier != multiplier {
+            if multiplier > combo_text.multiplier {
+                // Texts are scaled again once this is done, so it has to end at the scaled size
+                let font_size = COMBO_FONT_SIZE * accessibility.text_scale;
+                commands.insert_one(
+                    entity,
+                    animation::Tween::<animation::TextSizeLens>::new(
+                        font_size * 2.,
+                        font_size,
+                        0.4,
+                    )
+                    .with_ease(animation::Easing::EaseOutBounce),
+                );
+            }
+
+            combo_text.multiplier = multiplier;
+            *text = if multiplier > 1 {
+                LocalizedText::new("combo").with_arg("multiplier", multiplier)
+            } else {
+                LocalizedText::empty()
+            };
+        }
+    }
+}
+
 fn update_score_text(
     mut commands: Commands,
     mut score: ResMut<ScoreState>,
     score_events: Res<Events<ScoreChange>>,
-    mut score_query: Query<With<ScoreText, (&mut Text, Option<&animation::ChaseNumber>, Entity)>>,
+    mut score_query: Query<With<ScoreText, (&mut Text, Entity)>>,
 ) {
-    let mut old_score = None;
+    let mut jumped = false;
     for score_change in score.event_reader.iter(&score_events) {
-        old_score = Some(score.score);
-        match score_change {
+        match *score_change {
+            // Counted up by `finish_merges` once the merged squares reach the score
             ScoreChange::Add(s) => score.score += s,
-            ScoreChange::Reset => score.score = 0,
+            ScoreChange::Set(s) => {
+                score.score = s;
+                jumped = true;
+            }
+            ScoreChange::Reset => {
+                score.score = 0;
+                jumped = true;
+            }
         }
     }
-    for (mut text, chase_number, entity) in &mut score_query.iter() {
-        if let Some(old_score) = old_score {
-            commands.insert_one(
-                entity,
-                animation::ChaseNumber {
-                    duration: 0.5,
-                    delay: TIME_TO_DIE as f32,
-                    start_number: old_score as f32,
-                    end_number: score.score as f32,
-                    cur_number: old_score as f32,
-                    ease: animation::Easing::EaseInCirc,
-                    ..Default::default()
-                },
-            );
-            text.value = old_score.to_string();
-        } else {
-            text.value = (chase_number
-                .map(|c| c.cur_number as u64)
-                .unwrap_or(score.score))
-            .to_string();
+    if jumped {
+        score.shown = score.score;
+        for (mut text, entity) in &mut score_query.iter() {
+            commands.remove_one::<animation::Tween<animation::TextNumberLens>>(entity);
+            text.value = score.shown.to_string();
+        }
+    }
+}
+
+/// Where merged squares fly to, kept in sync with the score text in the UI
+#[derive(Default)]
+struct ScoreTarget {
+    /// The center of the score text, in the coordinates of the board
+    position: Vec3,
+    event_reader: EventReader<SquareEvent>,
+    finished_reader: EventReader<animation::AnimationFinished>,
+}
+
+/// A merged square on its way to the score with the points it made, removed once its merge
+/// sequence has finished
+struct FlyingToScore {
+    points: u64,
+}
+
+fn update_score_target(
+    windows: Res<Windows>,
+    mut target: ResMut<ScoreTarget>,
+    mut score_query: Query<With<ScoreText, &Transform>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let window_size = Vec2::new(window.width as f32, window.height as f32);
+
+    // UI nodes are laid out from the bottom left of the window, the board from its center
+    for transform in &mut score_query.iter() {
+        let position = transform.value.w_axis().truncate();
+        target.position = Vec3::new(
+            position.x() - window_size.x() / 2.,
+            position.y() - window_size.y() / 2.,
+            0.,
+        );
+    }
+}
+
+/// Removes merged squares once they have reached the score and counts their points up
+fn finish_merges(
+    mut commands: Commands,
+    mut target: ResMut<ScoreTarget>,
+    mut score: ResMut<ScoreState>,
+    finished_events: Res<Events<animation::AnimationFinished>>,
+    flying_query: Query<&FlyingToScore>,
+    mut score_query: Query<With<ScoreText, (&Text, Entity)>>,
+) {
+    let mut reached = 0;
+    for finished in target.finished_reader.iter(&finished_events) {
+        if finished.kind != animation::AnimationKind::Sequence {
+            continue;
+        }
+        if let Ok(flying) = flying_query.get::<FlyingToScore>(finished.entity) {
+            reached += flying.points;
+            commands.despawn_recursive(finished.entity);
+        }
+    }
+    if reached == 0 {
+        return;
+    }
+
+    let target_score = (score.shown + reached).min(score.score);
+    for (text, entity) in &mut score_query.iter() {
+        let displayed = text.value.parse().unwrap_or(score.shown as f32);
+        commands.insert_one(
+            entity,
+            animation::Tween::<animation::TextNumberLens>::new(displayed, target_score as f32, 0.5)
+                .with_ease(animation::Easing::EaseInCirc),
+        );
+    }
+    score.shown = target_score;
+}
+
+/// Sends merged squares flying to the score, which counts up their points once they arrive, and
+/// shrinks cleared squares away
+///
+/// With reduced motion both fade out where they are instead.
+fn animate_removals(
+    mut commands: Commands,
+    mut target: ResMut<ScoreTarget>,
+    settings: Res<animation::AnimationSettings>,
+    square_events: Res<Events<SquareEvent>>,
+    translation_query: Query<&Translation>,
+) {
+    let score_position = target.position;
+
+    for event in target.event_reader.iter(&square_events) {
+        match *event {
+            SquareEvent::Merged { merged, points, .. } if settings.reduced_motion => {
+                let merge = animation::Sequence::new().wait(FADE_DURATION);
+                commands.insert(merged, (merge, FadeOut, FlyingToScore { points }));
+            }
+            SquareEvent::Merged { merged, points, .. } => {
+                let start = match translation_query.get::<Translation>(merged) {
+                    Ok(translation) => translation.0,
+                    Err(_) => continue,
+                };
+
+                // Once the other square has slid on top of it, it pops and flies to the score
+                let merge = animation::Sequence::new()
+                    .wait(SLIDE_DURATION)
+                    .then(
+                        animation::Tween::<animation::ScaleLens>::new(1., 1.2, POP_DURATION)
+                            .with_ease(animation::Easing::EaseOutQuad),
+                    )
+                    .then(
+                        animation::Tween::<animation::TranslationLens>::new(
+                            start,
+                            score_position,
+                            TIME_TO_DIE,
+                        )
+                        .with_ease(animation::Easing::EaseOutBack),
+                    )
+                    .together(
+                        animation::Tween::<animation::ScaleLens>::new(1.2, 0., TIME_TO_DIE)
+                            .with_ease(animation::Easing::EaseInOutCirc),
+                    );
+                commands.insert(merged, (merge, FlyingToScore { points }));
+            }
+            SquareEvent::Cleared { entity } if settings.reduced_motion => {
+                let clear = animation::Sequence::new()
+                    .wait(FADE_DURATION)
+                    .on_finish(animation::OnFinish::Despawn);
+                commands.insert(entity, (clear, FadeOut));
+            }
+            SquareEvent::Cleared { entity } => {
+                commands.insert_one(
+                    entity,
+                    animation::Tween::<animation::ScaleLens>::new(1., 0., TIME_TO_DIE)
+                        .with_ease(animation::Easing::EaseInOutCirc)
+                        .on_finish(animation::OnFinish::Despawn),
+                );
+            }
+            SquareEvent::Spawned { .. } => {}
         }
     }
 }
@@ -486,7 +1120,7 @@ struct Grid([Option<Square>; (GRID_SIZE * GRID_SIZE) as usize]);
 #[derive(Clone, PartialEq, Debug)]
 struct Square {
     entity: Entity,
-    score: u64,
+    tile: Tile,
 }
 
 impl Grid {
@@ -524,53 +1158,53 @@ impl Grid {
         inserted
     }
 
-    fn is_filled(&self, (x, y): (u32, u32)) -> bool {
-        self.get_at((x, y)).is_some()
+    /// The tiles of this grid, to be used with the rules
+    fn board(&self) -> Board {
+        let mut board = Board::new(GRID_SIZE);
+        for x in 0..GRID_SIZE {
+            for y in 0..GRID_SIZE {
+                board.set_tile((x, y), self.get_at((x, y)).map(|square| square.tile));
+            }
+        }
+        board
     }
 
-    fn move_to(&mut self, square: Square, new_pos: (u32, u32)) {
-        self.add_at(new_pos, square);
+    /// The current position in the notation of `bevy_squares::notation`
+    fn to_notation(&self, score: u64, moves: u32, seed: u64) -> String {
+        Position {
+            board: self.board(),
+            score,
+            moves,
+            seed: Some(seed),
+        }
+        .to_string()
     }
 
-    fn get_neighbors(&self, pos: (u32, u32)) -> [Option<&Square>; 4] {
-        [
-            self.get_at((pos.0 + 1, pos.1)),
-            pos.0.checked_sub(1).and_then(|it| self.get_at((it, pos.1))),
-            self.get_at((pos.0, pos.1 + 1)),
-            pos.1.checked_sub(1).and_then(|it| self.get_at((pos.0, it))),
-        ]
+    fn parse_notation(notation: &str) -> Result<Position, String> {
+        notation
+            .trim()
+            .parse::<Position>()
+            .map_err(|err| err.to_string())
     }
 }
 
-struct KillAfter {
-    timer: Timer,
-}
+/// Marks squares which are no longer part of the grid and only finish their animation
+struct Dying;
 
-impl KillAfter {
-    fn new(duration: f32) -> KillAfter {
-        KillAfter {
-            timer: Timer::from_seconds(duration, false),
-        }
-    }
-}
+/// Marks squares spawned or moved this frame, which fade in with reduced motion instead of
+/// popping up or sliding
+struct FadeIn;
 
-fn kill_after_update(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut kill_query: Query<With<GameSquare, (Entity, &mut KillAfter)>>,
-) {
-    for (entity, mut kill_after) in &mut kill_query.iter() {
-        if kill_after.timer.finished {
-            commands.despawn_recursive(entity);
-        }
+/// Marks removed squares which fade out before they are despawned, only used with reduced motion
+struct FadeOut;
 
-        kill_after.timer.tick(time.delta_seconds);
-    }
-}
+/// The camera showing the board, as opposed to the UI camera
+struct MainCamera;
 
 struct BackgroundSquare;
 struct GameSquare;
 struct SquareOutline;
+struct TileMarker;
 
 fn spawn_square(
     commands: &mut Commands,
@@ -578,12 +1212,15 @@ fn spawn_square(
     mut meshes: &mut ResMut<Assets<Mesh>>,
     colors: &SquareColors,
     pos: (u32, u32),
-    score: u64,
+    tile: Tile,
     direction: Option<MovementDirection>,
 ) {
     let (x, y) = calculate_grid_position(pos.0 as i32, pos.1 as i32);
     let outline_color = colors.get(SQUARE_OUTLINE_COLOR);
-    let color = colors.get(score);
+    let color = colors.tile_color(tile);
+    let marker_color = colors.marker_color(tile);
+    let center = Vec3::new(SQUARE_WIDTH as f32 / 2.0, SQUARE_WIDTH as f32 / 2.0, 0.1);
+    let marker_size = SQUARE_WIDTH as f32 * 0.2;
     let commands = commands
         .spawn((
             GameSquare,
@@ -591,6 +1228,7 @@ fn spawn_square(
             Translation::new(x, y, 1.0),
             Transform::default(),
             Scale::default(),
+            FadeIn,
         ))
         .with_children(|parent| {
             parent
@@ -623,6 +1261,44 @@ fn spawn_square(
                     ..Default::default()
                 })
                 .with(SquareOutline);
+
+            let marker = match tile.kind {
+                TileKind::Normal | TileKind::Wall => None,
+                TileKind::Wildcard => Some((
+                    ShapeType::Circle(marker_size),
+                    TessellationMode::Stroke(&StrokeOptions::default().with_line_width(6.0)),
+                )),
+                TileKind::Bomb => Some((
+                    ShapeType::Circle(marker_size),
+                    TessellationMode::Fill(&FillOptions::default()),
+                )),
+                TileKind::Multiplier => Some((
+                    ShapeType::Quad(
+                        Vec2::new(0., marker_size),
+                        Vec2::new(marker_size, 0.),
+                        Vec2::new(0., -marker_size),
+                        Vec2::new(-marker_size, 0.),
+                    ),
+                    TessellationMode::Fill(&FillOptions::default()),
+                )),
+            };
+
+            if let Some((shape, mode)) = marker {
+                parent
+                    .spawn(primitive(
+                        marker_color,
+                        &mut meshes,
+                        shape,
+                        mode,
+                        center.into(),
+                    ))
+                    .with(LocalTransform::default())
+                    .with(Draw {
+                        is_transparent: true,
+                        ..Default::default()
+                    })
+                    .with(TileMarker);
+            }
         });
 
     if let Some(direction) = direction {
@@ -647,20 +1323,54 @@ fn spawn_square(
     }
 
     let entity = commands.current_entity().unwrap();
-    grid.add_at(pos, Square { score, entity });
+    grid.add_at(pos, Square { tile, entity });
+}
+
+fn spawn_board(
+    commands: &mut Commands,
+    grid: &mut Grid,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    colors: &SquareColors,
+    board: &Board,
+    direction: Option<MovementDirection>,
+) {
+    for (pos, tile) in board.tiles() {
+        spawn_square(commands, grid, meshes, colors, pos, tile, direction);
+    }
 }
 
 fn update_colors(
     colors: Res<SquareColors>,
     grid: Res<Grid>,
-    mut query: Query<Without<KillAfter, With<GameSquare, (&GridPosition, &Children)>>>,
-    material_query: Query<Without<SquareOutline, (&mut Handle<ColorMaterial>, &Sprite)>>,
+    mut query: Query<Without<Dying, Without<FadeIn, With<GameSquare, (&GridPosition, &Children)>>>>,
+    // Fading squares have their own materials until they are done
+    material_query: Query<
+        Without<
+            SquareOutline,
+            Without<
+                animation::Tween<animation::MaterialColorLens>,
+                (&mut Handle<ColorMaterial>, &Sprite),
+            >,
+        >,
+    >,
+    marker_query: Query<&TileMarker>,
 ) {
     for (position, children) in &mut query.iter() {
-        let square = grid.get_at((position.0, position.1)).unwrap();
-        let color = colors.get(square.score);
+        // Debug builds report squares missing from the grid, see `consistency.rs`
+        let square = match grid.get_at((position.0, position.1)) {
+            Some(square) => square,
+            None => continue,
+        };
+        let color = colors.tile_color(square.tile);
+        let marker_color = colors.marker_color(square.tile);
 
         for &child in children.as_slice() {
+            let color = if marker_query.get::<TileMarker>(child).is_ok() {
+                &marker_color
+            } else {
+                &color
+            };
+
             if let Ok(mut handle) = material_query.get_mut::<Handle<ColorMaterial>>(child) {
                 *handle = color.clone();
             }
@@ -668,166 +1378,200 @@ fn update_colors(
     }
 }
 
+/// The materials given to the parts of fading squares, with the part using each of them
+#[derive(Default)]
+struct FadeMaterials(Vec<(Entity, Handle<ColorMaterial>)>);
+
+/// Gives the parts of new or moved squares their own materials and fades them in, and the parts
+/// of removed squares out
+///
+/// The materials of the squares are shared, so fading them directly would fade every square.
+fn fade_squares(
+    mut commands: Commands,
+    settings: Res<animation::AnimationSettings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut fade_materials: ResMut<FadeMaterials>,
+    mut fade_in_query: Query<With<FadeIn, (Entity, &Children)>>,
+    mut fade_out_query: Query<With<FadeOut, (Entity, &Children)>>,
+    part_query: Query<(&mut Handle<ColorMaterial>, &mut Draw)>,
+) {
+    for (entity, children) in &mut fade_in_query.iter() {
+        commands.remove_one::<FadeIn>(entity);
+        if settings.reduced_motion {
+            for &child in children.as_slice() {
+                fade_part(
+                    &mut commands,
+                    &mut materials,
+                    &mut fade_materials,
+                    &part_query,
+                    child,
+                    true,
+                );
+            }
+        }
+    }
+
+    for (entity, children) in &mut fade_out_query.iter() {
+        commands.remove_one::<FadeOut>(entity);
+        for &child in children.as_slice() {
+            fade_part(
+                &mut commands,
+                &mut materials,
+                &mut fade_materials,
+                &part_query,
+                child,
+                false,
+            );
+        }
+    }
+}
+
+/// Fades the part from transparent to its color, or the other way around
+fn fade_part(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    fade_materials: &mut FadeMaterials,
+    part_query: &Query<(&mut Handle<ColorMaterial>, &mut Draw)>,
+    part: Entity,
+    fade_in: bool,
+) {
+    let mut handle = match part_query.get_mut::<Handle<ColorMaterial>>(part) {
+        Ok(handle) => handle,
+        Err(_) => return,
+    };
+    let color = match materials.get(&handle) {
+        Some(material) => material.color,
+        None => return,
+    };
+    let transparent = Color::rgba(color.r, color.g, color.b, 0.);
+    let (from, to) = if fade_in {
+        (transparent, color)
+    } else {
+        (color, transparent)
+    };
+
+    *handle = materials.add(from.into());
+    fade_materials.0.push((part, handle.clone()));
+    if let Ok(mut draw) = part_query.get_mut::<Draw>(part) {
+        draw.is_transparent = true;
+    }
+    commands.insert_one(
+        part,
+        animation::Tween::<animation::MaterialColorLens>::new(from, to, FADE_DURATION),
+    );
+}
+
+/// Removes the materials of fades once their part went back to the shared material or is gone
+///
+/// Runs after rendering, so that no material is removed while it is still drawn.
+fn remove_fade_materials(
+    mut fade_materials: ResMut<FadeMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    handle_query: Query<&Handle<ColorMaterial>>,
+) {
+    fade_materials.0.retain(|(part, fade)| {
+        let in_use = handle_query
+            .get::<Handle<ColorMaterial>>(*part)
+            .map_or(false, |handle| *handle == *fade);
+        if !in_use {
+            materials.remove(fade);
+        }
+        in_use
+    });
+}
+
 fn sync_square_grid_position(
     mut commands: Commands,
+    settings: Res<animation::AnimationSettings>,
     mut query: Query<With<GameSquare, (Entity, Changed<GridPosition>, &Translation)>>,
+    new_query: Query<&FadeIn>,
 ) {
     for (entity, pos, translation) in &mut query.iter() {
+        // With reduced motion the square jumps to its cell, so it fades in there instead
+        if settings.reduced_motion && new_query.get::<FadeIn>(entity).is_err() {
+            commands.insert_one(entity, FadeIn);
+        }
+
         let (x, y) = calculate_grid_position(pos.0 as i32, pos.1 as i32);
-        let move_to = animation::MoveTo {
-            start_position: *translation,
-            end_position: Translation::new(x, y, 1.0),
-            duration: 0.15,
-            ease: animation::Easing::EaseOutBack,
-            ..Default::default()
-        };
+        let move_to = animation::Tween::<animation::TranslationLens>::new(
+            translation.0,
+            Vec3::new(x, y, 1.0),
+            SLIDE_DURATION,
+        )
+        .with_ease(animation::Easing::EaseOutBack)
+        .with_interrupt(animation::Interrupt::Blend(SLIDE_DURATION / 2.));
 
-        commands.insert_one(entity, move_to);
+        commands.play(entity, move_to);
     }
 }
 
-enum MovementDirection {
-    Up,
-    Down,
-    Right,
-    Left,
-}
-
 fn move_squares(
     mut commands: Commands,
-    game_state: Res<GameState>,
+    mut game_state: ResMut<GameState>,
     colors: Res<SquareColors>,
+    mut session: ResMut<GameSession>,
     mut grid: ResMut<Grid>,
     mut score_events: ResMut<Events<ScoreChange>>,
     mut game_events: ResMut<Events<RunningGameState>>,
+    mut square_events: ResMut<Events<SquareEvent>>,
     mut meshes: ResMut<Assets<Mesh>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    query: Query<With<GameSquare, (&mut GridPosition, &Children, &Translation)>>,
-    mut background_query: Query<With<BackgroundSquare, (Entity, &GridPosition, &Translation)>>,
+    move_requests: Res<Events<MoveRequest>>,
+    query: Query<With<GameSquare, &mut GridPosition>>,
+    mut background_query: Query<With<BackgroundSquare, (Entity, &GridPosition)>>,
 ) {
-    if game_state.state == RunningGameState::GameOver {
-        return;
-    }
-
-    let direction = if keyboard_input.just_pressed(KeyCode::Up) {
-        MovementDirection::Up
-    } else if keyboard_input.just_pressed(KeyCode::Down) {
-        MovementDirection::Down
-    } else if keyboard_input.just_pressed(KeyCode::Right) {
-        MovementDirection::Right
-    } else if keyboard_input.just_pressed(KeyCode::Left) {
-        MovementDirection::Left
-    } else {
-        return;
-    };
-
-    let mut scores = HashSet::new();
-    let mut moved_squares = HashSet::new();
-
-    enum GridCommand {
-        MoveTo { to: (u32, u32), square: Square },
-    };
-
-    let x_iter: Box<dyn Iterator<Item = u32>> = match direction {
-        MovementDirection::Up | MovementDirection::Left => Box::new(0..GRID_SIZE),
-        MovementDirection::Down | MovementDirection::Right => Box::new((0..GRID_SIZE).rev()),
+    // Requests are always read, so that none are left over for the next game
+    let request = game_state.move_reader.iter(&move_requests).next().copied();
+    let direction = match request {
+        Some(MoveRequest(direction)) if game_state.state == RunningGameState::Running => direction,
+        _ => return,
     };
 
-    let mut grid_commands = vec![];
-    for x in x_iter {
-        let y_iter: Box<dyn Iterator<Item = u32>> = match direction {
-            MovementDirection::Up | MovementDirection::Left => Box::new((0..GRID_SIZE).rev()),
-            MovementDirection::Down | MovementDirection::Right => Box::new(0..GRID_SIZE),
-        };
-        for y in y_iter {
-            let square = if let Some(square) = grid.get_at((x, y)) {
-                square
-            } else {
-                continue;
-            };
-
-            let mut pos = query.get_mut::<GridPosition>(square.entity).unwrap();
-
-            scores.insert(square.score);
-
-            let mut new_pos: GridPosition = *pos;
-
-            match direction {
-                MovementDirection::Up => {
-                    new_pos.1 = (pos.1 + 1).min(GRID_SIZE - 1);
-                }
-                MovementDirection::Down => {
-                    new_pos.1 = pos.1.saturating_sub(1);
-                }
-                MovementDirection::Right => {
-                    new_pos.0 = (pos.0 + 1).min(GRID_SIZE - 1);
-                }
-                MovementDirection::Left => {
-                    new_pos.0 = pos.0.saturating_sub(1);
-                }
-            }
-
-            if *pos == new_pos {
-                continue;
-            }
+    let session = &mut *session;
+    let mut board = grid.board();
+    let turn = board.play(direction, &mut session.spawner, &mut session.rng.rng);
+    let multiplier = session.combo.apply(&turn);
 
-            let mut square_score = square.score;
-
-            if let Some(other_square) = grid.get_at((new_pos.0, new_pos.1)) {
-                if other_square.score != square.score {
-                    continue;
-                }
+    let mut moved_squares = HashSet::new();
+    let mut moved = vec![];
+
+    for square_move in &turn.moves {
+        if square_move.merged {
+            // The square we merge with has not moved this turn, so it is still in the grid
+            let other_square = grid.get_at(square_move.to).unwrap();
+            commands.insert_one(other_square.entity, Dying);
+            score_events.send(ScoreChange::Add(square_move.tile.score * multiplier));
+
+            square_events.send(SquareEvent::Merged {
+                entity: grid.get_at(square_move.from).unwrap().entity,
+                merged: other_square.entity,
+                position: square_move.to,
+                tile: square_move.tile,
+                points: square_move.tile.score * multiplier,
+            });
+        }
 
-                let current_pos = query.get::<Translation>(other_square.entity).unwrap();
+        let mut square = grid.take_at(square_move.from).unwrap();
+        square.tile = square_move.tile;
 
-                commands.insert(
-                    other_square.entity,
-                    (
-                        KillAfter::new(TIME_TO_DIE),
-                        animation::ScaleTo {
-                            start_scale: Scale(1.),
-                            end_scale: Scale(0.),
-                            duration: TIME_TO_DIE,
-                            ease: animation::Easing::EaseInOutCirc,
-                            ..Default::default()
-                        },
-                        animation::MoveTo {
-                            start_position: *current_pos,
-                            end_position: Translation::new(
-                                SQUARE_WIDTH as f32 / 2.0 + SQUARE_WIDTH as f32 / -2.,
-                                SQUARE_WIDTH as f32 / 2.0 + WINDOW_HEIGHT as f32 / 2.0
-                                    - UI_OFFSET as f32,
-                                0.,
-                            ),
-                            duration: TIME_TO_DIE,
-                            ease: animation::Easing::EaseOutBack,
-                            ..Default::default()
-                        },
-                    ),
-                );
-                square_score += other_square.score;
-                score_events.send(ScoreChange::Add(square_score));
-            }
-
-            let mut square = grid.take_at((pos.0, pos.1)).unwrap();
-            square.score = square_score;
+        let new_pos = GridPosition(square_move.to.0, square_move.to.1);
+        *query.get_mut::<GridPosition>(square.entity).unwrap() = new_pos;
+        moved_squares.insert(new_pos);
+        moved.push((square_move.to, square));
+    }
 
-            *pos = new_pos;
-            grid_commands.push(GridCommand::MoveTo {
-                to: (new_pos.0, new_pos.1),
-                square,
-            });
-            moved_squares.insert(*pos);
-        }
+    for (to, square) in moved {
+        grid.add_at(to, square);
     }
 
-    for cmd in grid_commands {
-        match cmd {
-            GridCommand::MoveTo { to, square } => grid.move_to(square, to),
-        }
+    for &pos in &turn.cleared {
+        let square = grid.take_at(pos).unwrap();
+        commands.insert_one(square.entity, Dying);
+        square_events.send(SquareEvent::Cleared {
+            entity: square.entity,
+        });
     }
 
-    for (entity, grid_pos, translation) in &mut background_query.iter() {
+    for (entity, grid_pos) in &mut background_query.iter() {
         if moved_squares.contains(grid_pos) {
             let mag = 5.;
             let bump = match direction {
@@ -836,92 +1580,45 @@ fn move_squares(
                 MovementDirection::Right => Vec3::new(1., 0., 0.),
                 MovementDirection::Left => Vec3::new(-1., 0., 0.),
             };
-            let end_position = translation.0 + bump * mag;
-
             let (x, y) = calculate_grid_position(grid_pos.0 as i32, grid_pos.1 as i32);
-            commands.insert_one(
+            let rest_position = Vec3::new(x, y, 0.);
+
+            // Bumps from quick moves play one after another instead of cutting each other off
+            commands.play(
                 entity,
-                animation::MoveTo {
-                    start_position: Translation::new(x, y, 0.),
-                    end_position: Translation(end_position),
-                    duration: 0.1,
-                    bounce: true,
-                    loop_count: 1,
-                    ..Default::default()
-                },
+                animation::Tween::<animation::TranslationLens>::new(
+                    rest_position,
+                    rest_position + bump * mag,
+                    0.1,
+                )
+                .with_repeat(animation::Repeat::Times(1))
+                .with_ping_pong()
+                .with_interrupt(animation::Interrupt::Queue),
             );
         }
     }
 
-    let mut rng = thread_rng();
-
-    let mut possible_coords: Vec<u32> = (0..GRID_SIZE).collect();
-    let coords = loop {
-        if possible_coords.is_empty() {
-            break None;
-        }
-        if moved_squares.is_empty() {
-            break None;
-        }
-
-        possible_coords.shuffle(&mut rng);
-
-        let random_pos = possible_coords.pop().unwrap();
-
-        let coords = match direction {
-            MovementDirection::Up => (random_pos, 0),
-            MovementDirection::Down => (random_pos, GRID_SIZE - 1),
-            MovementDirection::Right => (0, random_pos),
-            MovementDirection::Left => (GRID_SIZE - 1, random_pos),
-        };
-
-        if grid.is_filled(coords) {
-            continue;
-        } else {
-            break Some(coords);
-        }
-    };
-
-    if let Some(coords) = coords {
-        let mut scores: Vec<u64> = scores.iter().copied().collect();
-
-        scores.sort();
-
-        scores.truncate((scores.len() / 3).max(1));
+    if turn.moved() {
+        session.stats.moves += 1;
+        session.history.push(direction);
+    }
 
+    if let Some(spawn) = turn.spawn {
         spawn_square(
             &mut commands,
             &mut grid,
             &mut meshes,
             &colors,
-            coords,
-            *scores.choose(&mut rng).unwrap(),
+            spawn.position,
+            spawn.tile,
             Some(direction),
         );
-    } else {
-        let mut found = false;
-        'outer: for x in 0..GRID_SIZE {
-            for y in 0..GRID_SIZE {
-                if let Some(square) = grid.get_at((x, y)) {
-                    let neighbors = grid.get_neighbors((x, y));
-
-                    for neighbor in &neighbors {
-                        if let Some(neighbor) = neighbor {
-                            if neighbor.score == square.score {
-                                found = true;
-                                break 'outer;
-                            }
-                        }
-                    }
-                } else {
-                    found = true;
-                    break 'outer;
-                }
-            }
-        }
+        square_events.send(SquareEvent::Spawned {
+            entity: grid.get_at(spawn.position).unwrap().entity,
+        });
+    }
 
-        if !found {
-            game_events.send(RunningGameState::GameOver);
-        }
+    if turn.stuck {
+        game_events.send(RunningGameState::GameOver);
     }
 }
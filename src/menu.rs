@@ -0,0 +1,196 @@
+use crate::localization::LocalizedText;
+use crate::settings::OpenSettings;
+use crate::theme::{Theme, Themed};
+use crate::{GameMode, GameRng, GameSession, RestartGame, RunningGameState, StartingPosition};
+use bevy::prelude::*;
+
+#[derive(Default)]
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(main_menu_system.system());
+    }
+}
+
+struct MainMenuScreen;
+struct ModeButton(GameMode);
+struct SettingsButton;
+
+/// Lets the player pick a game mode, covering the whole window
+pub fn spawn_main_menu(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    theme: Theme,
+) {
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+    let button_material = materials.add(Color::rgba(1., 1., 1., 0.6).into());
+    let modes = [
+        (GameMode::Classic, "mode-classic"),
+        (GameMode::Daily, "mode-daily"),
+        (GameMode::Arcade, "mode-arcade"),
+        (GameMode::Puzzle, "mode-puzzle"),
+    ];
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect::all(Val::Px(0.)),
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(theme.background().into()),
+            ..Default::default()
+        })
+        .with(MainMenuScreen)
+        .with(Themed::Background)
+        .with_children(|parent| {
+            parent
+                .spawn(TextComponents {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value: String::new(),
+                        font: font_handle.clone(),
+                        style: TextStyle {
+                            font_size: 40.0,
+                            color: theme.text(),
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(LocalizedText::new("menu-title"))
+                .with(Themed::Text);
+
+            for &(mode, key) in modes.iter() {
+                parent
+                    .spawn(ButtonComponents {
+                        style: Style {
+                            margin: Rect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            padding: Rect::all(Val::Px(10.)),
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .with(ModeButton(mode))
+                    .with_children(|parent| {
+                        parent
+                            .spawn(TextComponents {
+                                text: Text {
+                                    value: String::new(),
+                                    font: font_handle.clone(),
+                                    style: TextStyle {
+                                        font_size: 25.,
+                                        color: Color::rgb(0., 0., 0.),
+                                    },
+                                },
+                                ..Default::default()
+                            })
+                            .with(LocalizedText::new(key));
+                    });
+            }
+
+            parent
+                .spawn(ButtonComponents {
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(20.0),
+                            ..Rect::all(Val::Px(5.0))
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(10.)),
+                        ..Default::default()
+                    },
+                    material: button_material.clone(),
+                    ..Default::default()
+                })
+                .with(SettingsButton)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextComponents {
+                            text: Text {
+                                value: String::new(),
+                                font: font_handle.clone(),
+                                style: TextStyle {
+                                    font_size: 20.,
+                                    color: Color::rgb(0., 0., 0.),
+                                },
+                            },
+                            ..Default::default()
+                        })
+                        .with(LocalizedText::new("settings"));
+                });
+        });
+}
+
+fn main_menu_system(
+    mut commands: Commands,
+    mut game_mode: ResMut<GameMode>,
+    mut starting_position: ResMut<StartingPosition>,
+    mut session: ResMut<GameSession>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut game_events: ResMut<Events<RunningGameState>>,
+    mut settings_events: ResMut<Events<OpenSettings>>,
+    mut button_query: Query<(&Interaction, &ModeButton)>,
+    mut settings_query: Query<With<SettingsButton, &Interaction>>,
+    mut screen_query: Query<With<MainMenuScreen, Entity>>,
+) {
+    // The settings screen brings the menu back once it is closed
+    for interaction in &mut settings_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            settings_events.send(OpenSettings);
+            for entity in &mut screen_query.iter() {
+                commands.despawn_recursive(entity);
+            }
+            return;
+        }
+    }
+
+    let mut selected = None;
+    for (interaction, mode_button) in &mut button_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            selected = Some(mode_button.0);
+        }
+    }
+
+    let mode = match selected {
+        Some(mode) => mode,
+        None => return,
+    };
+
+    *game_mode = mode;
+    session.rng = match mode {
+        GameMode::Daily => GameRng::daily(),
+        _ => GameRng::new(None),
+    };
+    session.custom_start = false;
+    *starting_position = match mode {
+        GameMode::Puzzle => StartingPosition::empty(),
+        GameMode::Arcade => StartingPosition::arcade(),
+        GameMode::Classic | GameMode::Daily => StartingPosition::classic(),
+    };
+
+    // Puzzles first need a level, which restarts the game once chosen
+    if mode == GameMode::Puzzle {
+        game_events.send(RunningGameState::LevelSelect);
+    } else {
+        restart_events.send(RestartGame);
+    }
+
+    for entity in &mut screen_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
@@ -0,0 +1,217 @@
+//! A compact text notation for positions, to share them in bug reports or write them in tests
+//!
+//! A position is its size, its rows from the top separated by `/` with the cells of a row
+//! separated by `,`, followed by the score, the number of moves and optionally the seed:
+//!
+//! ```text
+//! 4x4 .,.,.,./.,1,.,./.,.,2,./.,.,.,. score:0 moves:0 seed:42
+//! ```
+//!
+//! Empty cells are `.`, squares are written like everywhere else, see the `Display` of [`Tile`].
+
+use crate::rules::{Board, Tile};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Position {
+    pub board: Board,
+    pub score: u64,
+    pub moves: u32,
+    /// The seed of the game the position comes from, if it is known
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParsePositionError(String);
+
+impl fmt::Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid position: {}", self.0)
+    }
+}
+
+fn error(message: impl Into<String>) -> ParsePositionError {
+    ParsePositionError(message.into())
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = self.board.size();
+        write!(f, "{0}x{0} ", size)?;
+
+        for row in 0..size {
+            if row > 0 {
+                write!(f, "/")?;
+            }
+
+            // Rows are written from the top, like the board is seen
+            let y = size - 1 - row;
+            for x in 0..size {
+                if x > 0 {
+                    write!(f, ",")?;
+                }
+                match self.board.tile((x, y)) {
+                    Some(tile) => write!(f, "{}", tile)?,
+                    None => write!(f, ".")?,
+                }
+            }
+        }
+
+        write!(f, " score:{} moves:{}", self.score, self.moves)?;
+        if let Some(seed) = self.seed {
+            write!(f, " seed:{}", seed)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Position {
+    type Err = ParsePositionError;
+
+    fn from_str(s: &str) -> Result<Position, ParsePositionError> {
+        let mut parts = s.split_whitespace();
+        let size = parse_size(parts.next().ok_or_else(|| error("it is empty"))?)?;
+        let board = parse_rows(parts.next().ok_or_else(|| error("missing rows"))?, size)?;
+
+        let mut position = Position {
+            board,
+            score: 0,
+            moves: 0,
+            seed: None,
+        };
+
+        for part in parts {
+            let mut key_value = part.splitn(2, ':');
+            let (key, value) = match (key_value.next(), key_value.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => return Err(error(format!("expected `key:value`, found `{}`", part))),
+            };
+            let invalid = || error(format!("invalid {} `{}`", key, value));
+
+            match key {
+                "score" => position.score = value.parse().map_err(|_| invalid())?,
+                "moves" => position.moves = value.parse().map_err(|_| invalid())?,
+                "seed" => position.seed = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        Ok(position)
+    }
+}
+
+/// Boards are always square, but the size is written as `<width>x<height>` to be obvious
+fn parse_size(value: &str) -> Result<u32, ParsePositionError> {
+    let mut parts = value.splitn(2, 'x').map(|part| part.parse::<u32>());
+
+    match (parts.next(), parts.next()) {
+        (Some(Ok(width)), Some(Ok(height))) if width == height && width > 0 => Ok(width),
+        (Some(Ok(_)), Some(Ok(_))) => Err(error("only square boards are supported")),
+        _ => Err(error(format!("invalid size `{}`", value))),
+    }
+}
+
+fn parse_rows(value: &str, size: u32) -> Result<Board, ParsePositionError> {
+    let rows: Vec<&str> = value.split('/').collect();
+    if rows.len() != size as usize {
+        return Err(error(format!(
+            "expected {} rows, found {}",
+            size,
+            rows.len()
+        )));
+    }
+
+    let mut board = Board::new(size);
+    for (row, cells) in rows.iter().enumerate() {
+        let cells: Vec<&str> = cells.split(',').collect();
+        if cells.len() != size as usize {
+            return Err(error(format!(
+                "expected {} cells in row {}, found {}",
+                size,
+                row + 1,
+                cells.len()
+            )));
+        }
+
+        let y = size - 1 - row as u32;
+        for (x, cell) in cells.iter().enumerate() {
+            let tile = match *cell {
+                "." => None,
+                tile => Some(tile.parse::<Tile>().map_err(|err| error(err.to_string()))?),
+            };
+            board.set_tile((x as u32, y), tile);
+        }
+    }
+
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::TileKind;
+
+    #[test]
+    fn positions_are_read_from_the_top() {
+        let position: Position = "2x2 1,#/.,b4 score:12 moves:3 seed:42".parse().unwrap();
+
+        let mut board = Board::new(2);
+        board.set((0, 1), Some(1));
+        board.set_tile((1, 1), Some(Tile::wall()));
+        board.set_tile(
+            (1, 0),
+            Some(Tile {
+                kind: TileKind::Bomb,
+                score: 4,
+            }),
+        );
+
+        assert_eq!(
+            position,
+            Position {
+                board,
+                score: 12,
+                moves: 3,
+                seed: Some(42),
+            }
+        );
+    }
+
+    #[test]
+    fn formatted_positions_parse_back() {
+        let mut board = Board::new(4);
+        board.set((1, 2), Some(16));
+        board.set_tile((3, 3), Some(Tile::wildcard()));
+        let position = Position {
+            board,
+            score: 30,
+            moves: 7,
+            seed: None,
+        };
+
+        let notation = position.to_string();
+
+        assert_eq!(
+            notation,
+            "4x4 .,.,.,*/.,16,.,./.,.,.,./.,.,.,. score:30 moves:7"
+        );
+        assert_eq!(notation.parse(), Ok(position));
+    }
+
+    #[test]
+    fn invalid_positions_are_rejected() {
+        for notation in &[
+            "",
+            "4x4",
+            "3x4 .,.,./.,.,./.,.,.",
+            "2x2 .,./.",
+            "2x2 .,./.,.,.",
+            "2x2 .,./.,y",
+            "2x2 .,./.,. score:lots",
+            "2x2 .,./.,. level:3",
+        ] {
+            assert!(notation.parse::<Position>().is_err(), "{}", notation);
+        }
+    }
+}
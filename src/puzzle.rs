@@ -0,0 +1,756 @@
+use crate::hint::HintSettings;
+use crate::localization::LocalizedText;
+use crate::rules::{Board, MovementDirection, SpawnPolicy, SpawnRng, Spawner, Tile};
+use crate::theme::{Theme, Themed};
+use crate::{
+    storage, GameMode, GameOverScreen, GameSession, GameState, Grid, RestartGame, RunningGameState,
+    StartingPosition, GRID_SIZE,
+};
+use bevy::prelude::*;
+use rand::SeedableRng;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+const LEVEL_DIR: &str = "assets/levels";
+const LEVEL_EXTENSION: &str = "level";
+const BEST_RESULTS_FILE: &str = "puzzle_best.txt";
+/// How many moves the validator looks ahead for levels without a move limit
+const MAX_SOLVE_DEPTH: u32 = 12;
+
+#[derive(Default)]
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PuzzleState>()
+            .add_startup_system(setup_puzzles.system())
+            .add_system(level_select_system.system())
+            .add_system(back_to_levels_system.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, show_level_select.system())
+            .add_system_to_stage(bevy::app::stage::POST_UPDATE, check_goal.system());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Goal {
+    /// Have at most this many squares left on the board
+    ClearTo(usize),
+    /// Have a square with at least this score
+    Reach(u64),
+}
+
+impl Goal {
+    pub fn is_met(&self, board: &Board) -> bool {
+        match *self {
+            Goal::ClearTo(squares) => board.square_count() <= squares,
+            Goal::Reach(score) => board.highest_score().map_or(false, |it| it >= score),
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Goal::ClearTo(1) => write!(f, "Clear to one square"),
+            Goal::ClearTo(squares) => write!(f, "Clear to {} squares", squares),
+            Goal::Reach(score) => write!(f, "Make {}", score),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    pub board: Board,
+    pub spawns: SpawnPolicy,
+    pub goal: Goal,
+    pub move_limit: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct LevelError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn level_error(line: usize, message: impl Into<String>) -> LevelError {
+    LevelError {
+        line,
+        message: message.into(),
+    }
+}
+
+impl Level {
+    /// Parses a level file
+    ///
+    /// ```text
+    /// # Lines starting with a hash are comments
+    /// name: Double Up
+    /// goal: reach 16
+    /// moves: 3
+    /// spawns: 1 2
+    /// board:
+    /// . . . .
+    /// . # . .
+    /// 4 4 . *
+    /// 8 . b2 x2
+    /// ```
+    ///
+    /// Goals are either `reach <score>` or `clear <squares>`, the move limit is optional and
+    /// `spawns` is either `none` or a list of tiles to spawn in order.
+    ///
+    /// The board is written as seen on screen, so the first row is the top of the grid. Empty
+    /// cells are `.`, everything else is a tile as written by [`Tile`]'s `Display`.
+    pub fn parse(source: &str) -> Result<Level, LevelError> {
+        let mut name = None;
+        let mut goal = None;
+        let mut move_limit = None;
+        let mut spawns = SpawnPolicy::Disabled;
+        let mut rows = vec![];
+        let mut in_board = false;
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.trim();
+            if line.is_empty() || (!in_board && line.starts_with('#')) {
+                continue;
+            }
+
+            if in_board {
+                rows.push((line_number, line));
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| level_error(line_number, "expected `key: value`"))?
+                .trim();
+
+            match key {
+                "name" => name = Some(value.to_string()),
+                "goal" => {
+                    goal = Some(parse_goal(value).ok_or_else(|| {
+                        level_error(line_number, format!("unknown goal `{}`", value))
+                    })?)
+                }
+                "moves" => {
+                    move_limit = Some(value.parse().map_err(|_| {
+                        level_error(line_number, format!("invalid move limit `{}`", value))
+                    })?)
+                }
+                "spawns" if value == "none" => spawns = SpawnPolicy::Disabled,
+                "spawns" => {
+                    let sequence = value
+                        .split_whitespace()
+                        .map(|tile| tile.parse::<Tile>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|err| level_error(line_number, err.to_string()))?;
+                    spawns = SpawnPolicy::Sequence(sequence);
+                }
+                "board" => in_board = true,
+                _ => return Err(level_error(line_number, format!("unknown key `{}`", key))),
+            }
+        }
+
+        let board = parse_board(&rows)?;
+
+        Ok(Level {
+            name: name.ok_or_else(|| level_error(0, "missing `name`"))?,
+            goal: goal.ok_or_else(|| level_error(0, "missing `goal`"))?,
+            board,
+            spawns,
+            move_limit,
+        })
+    }
+
+    fn is_failed(&self, moves: u32) -> bool {
+        self.move_limit.map_or(false, |limit| moves >= limit)
+    }
+}
+
+fn parse_goal(value: &str) -> Option<Goal> {
+    let mut parts = value.split_whitespace();
+    let goal = match parts.next()? {
+        "reach" => Goal::Reach(parts.next()?.parse().ok()?),
+        "clear" => Goal::ClearTo(parts.next()?.parse().ok()?),
+        _ => return None,
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(goal)
+}
+
+fn parse_board(rows: &[(usize, &str)]) -> Result<Board, LevelError> {
+    if rows.len() != GRID_SIZE as usize {
+        return Err(level_error(
+            rows.first().map_or(0, |row| row.0),
+            format!("expected {} board rows, found {}", GRID_SIZE, rows.len()),
+        ));
+    }
+
+    let mut board = Board::new(GRID_SIZE);
+    for (row, &(line_number, line)) in rows.iter().enumerate() {
+        let cells: Vec<&str> = line.split_whitespace().collect();
+        if cells.len() != GRID_SIZE as usize {
+            return Err(level_error(
+                line_number,
+                format!("expected {} cells, found {}", GRID_SIZE, cells.len()),
+            ));
+        }
+
+        let y = GRID_SIZE - 1 - row as u32;
+        for (x, cell) in cells.iter().enumerate() {
+            let tile = match *cell {
+                "." => None,
+                tile => Some(
+                    tile.parse::<Tile>()
+                        .map_err(|err| level_error(line_number, err.to_string()))?,
+                ),
+            };
+            board.set_tile((x as u32, y), tile);
+        }
+    }
+
+    Ok(board)
+}
+
+/// Loads a board on its own, written like the board of a level, for example for `--load`
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn load_board(path: &Path) -> Result<Board, String> {
+    let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let rows: Vec<(usize, &str)> = source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    parse_board(&rows).map_err(|err| err.to_string())
+}
+
+/// Loads all levels from the level directory, sorted by their file name
+pub fn load_levels() -> Vec<Level> {
+    let mut paths: Vec<_> = match std::fs::read_dir(LEVEL_DIR) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == LEVEL_EXTENSION))
+            .collect(),
+        Err(err) => {
+            eprintln!("Could not read levels from {}: {}", LEVEL_DIR, err);
+            return vec![];
+        }
+    };
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| match load_level(path) {
+            Ok(level) => Some(level),
+            Err(err) => {
+                eprintln!("Could not load level {}: {}", path.display(), err);
+                None
+            }
+        })
+        .collect()
+}
+
+fn load_level(path: &Path) -> Result<Level, String> {
+    let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    Level::parse(&source).map_err(|err| err.to_string())
+}
+
+/// Finds the shortest sequence of moves that reaches the goal of the level, if there is one
+pub fn solve(level: &Level) -> Option<Vec<MovementDirection>> {
+    // Levels only have deterministic spawns, so the rng is never actually used
+    let mut rng = SpawnRng::seed_from_u64(0);
+    let spawner = Spawner::new(level.spawns.clone());
+    let max_depth = level.move_limit.unwrap_or(MAX_SOLVE_DEPTH);
+
+    for depth in 0..=max_depth {
+        let mut path = vec![];
+        let mut seen = HashSet::new();
+
+        if search(
+            level.goal,
+            &level.board,
+            &spawner,
+            depth,
+            &mut path,
+            &mut seen,
+            &mut rng,
+        ) {
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+fn search(
+    goal: Goal,
+    board: &Board,
+    spawner: &Spawner,
+    depth: u32,
+    path: &mut Vec<MovementDirection>,
+    seen: &mut HashSet<(Board, Spawner, u32)>,
+    rng: &mut SpawnRng,
+) -> bool {
+    if goal.is_met(board) {
+        return true;
+    }
+
+    if depth == 0 || !seen.insert((board.clone(), spawner.clone(), depth)) {
+        return false;
+    }
+
+    for &direction in MovementDirection::ALL.iter() {
+        let mut board = board.clone();
+        let mut spawner = spawner.clone();
+        if !board.play(direction, &mut spawner, rng).moved() {
+            continue;
+        }
+
+        path.push(direction);
+        if search(goal, &board, &spawner, depth - 1, path, seen, rng) {
+            return true;
+        }
+        path.pop();
+    }
+
+    false
+}
+
+/// Checks every level and prints the results, returns whether all of them are solvable
+pub fn validate_levels() -> bool {
+    let mut all_solvable = true;
+
+    for level in load_levels() {
+        match solve(&level) {
+            Some(solution) => println!("{}: solvable with {:?}", level.name, solution),
+            None => {
+                all_solvable = false;
+                println!("{}: NOT SOLVABLE", level.name);
+            }
+        }
+    }
+
+    all_solvable
+}
+
+#[derive(Default)]
+pub struct PuzzleState {
+    levels: Vec<Level>,
+    current: Option<usize>,
+    best_results: HashMap<String, u32>,
+    checked_moves: u32,
+    event_reader: EventReader<RunningGameState>,
+}
+
+impl PuzzleState {
+    fn load(&mut self) {
+        self.levels = load_levels();
+        self.best_results = load_best_results();
+    }
+
+    fn current_level(&self) -> Option<&Level> {
+        self.current.and_then(|idx| self.levels.get(idx))
+    }
+
+    fn record(&mut self, moves: u32) {
+        let name = match self.current_level() {
+            Some(level) => level.name.clone(),
+            None => return,
+        };
+
+        let best = self.best_results.entry(name).or_insert(moves);
+        *best = (*best).min(moves);
+
+        if let Err(err) = save_best_results(&self.best_results) {
+            eprintln!("Could not save puzzle results: {}", err);
+        }
+    }
+}
+
+fn load_best_results() -> HashMap<String, u32> {
+    let contents = storage::data_file(BEST_RESULTS_FILE)
+        .and_then(std::fs::read_to_string)
+        .unwrap_or_default();
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.rsplitn(2, '\t');
+            let moves = parts.next()?.parse().ok()?;
+            let name = parts.next()?.to_string();
+            Some((name, moves))
+        })
+        .collect()
+}
+
+fn save_best_results(best_results: &HashMap<String, u32>) -> std::io::Result<()> {
+    let contents: String = best_results
+        .iter()
+        .map(|(name, moves)| format!("{}\t{}\n", name, moves))
+        .collect();
+
+    std::fs::write(storage::data_file(BEST_RESULTS_FILE)?, contents)
+}
+
+struct LevelSelectScreen;
+struct LevelButton(usize);
+struct LevelsButton;
+
+fn setup_puzzles(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    mut puzzle_state: ResMut<PuzzleState>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+) {
+    if *game_mode != GameMode::Puzzle {
+        return;
+    }
+
+    puzzle_state.load();
+    spawn_level_select(
+        &mut commands,
+        &puzzle_state,
+        &mut materials,
+        &asset_server,
+        *theme,
+    );
+}
+
+fn spawn_level_select(
+    commands: &mut Commands,
+    puzzle_state: &PuzzleState,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    theme: Theme,
+) {
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+    let button_material = materials.add(Color::rgba(1., 1., 1., 0.6).into());
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect::all(Val::Px(0.)),
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(theme.background().into()),
+            ..Default::default()
+        })
+        .with(LevelSelectScreen)
+        .with(Themed::Background)
+        .with_children(|parent| {
+            parent
+                .spawn(TextComponents {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value: String::new(),
+                        font: font_handle.clone(),
+                        style: TextStyle {
+                            font_size: 40.0,
+                            color: theme.text(),
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(LocalizedText::new("puzzles-title"))
+                .with(Themed::Text);
+
+            for (idx, level) in puzzle_state.levels.iter().enumerate() {
+                let best = match puzzle_state.best_results.get(&level.name) {
+                    Some(moves) => LocalizedText::new("level-best").with_arg("moves", moves),
+                    None => LocalizedText::empty(),
+                };
+
+                parent
+                    .spawn(ButtonComponents {
+                        style: Style {
+                            margin: Rect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            padding: Rect::all(Val::Px(10.)),
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .with(LevelButton(idx))
+                    .with_children(|parent| {
+                        // The best result is its own text, after the goal
+                        for text in vec![level_text(level), best] {
+                            parent
+                                .spawn(TextComponents {
+                                    text: Text {
+                                        value: String::new(),
+                                        font: font_handle.clone(),
+                                        style: TextStyle {
+                                            font_size: 18.,
+                                            color: Color::rgb(0., 0., 0.),
+                                        },
+                                    },
+                                    ..Default::default()
+                                })
+                                .with(text);
+                        }
+                    });
+            }
+        });
+}
+
+/// The name and goal of a level, for its button
+fn level_text(level: &Level) -> LocalizedText {
+    let text = match level.goal {
+        Goal::ClearTo(1) => LocalizedText::new("level-clear-one"),
+        Goal::ClearTo(squares) => LocalizedText::new("level-clear").with_arg("squares", squares),
+        Goal::Reach(score) => LocalizedText::new("level-reach").with_arg("score", score),
+    };
+    text.with_arg("name", &level.name)
+}
+
+fn level_select_system(
+    mut commands: Commands,
+    mut puzzle_state: ResMut<PuzzleState>,
+    mut starting_position: ResMut<StartingPosition>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut button_query: Query<(&Interaction, &LevelButton)>,
+    mut screen_query: Query<With<LevelSelectScreen, Entity>>,
+) {
+    let mut selected = None;
+    for (interaction, level_button) in &mut button_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            selected = Some(level_button.0);
+        }
+    }
+
+    let level = match selected.and_then(|idx| puzzle_state.levels.get(idx)) {
+        Some(level) => level.clone(),
+        None => return,
+    };
+
+    *starting_position = StartingPosition {
+        board: level.board,
+        spawn_policy: level.spawns,
+        combo_rules: None,
+    };
+    puzzle_state.current = selected;
+    puzzle_state.checked_moves = 0;
+    restart_events.send(RestartGame);
+
+    for entity in &mut screen_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+fn back_to_levels_system(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut game_events: ResMut<Events<RunningGameState>>,
+    mut button_query: Query<With<LevelsButton, &Interaction>>,
+    mut scene_query: Query<With<GameOverScreen, Entity>>,
+) {
+    // Escape closes the settings screen instead
+    if *game_mode != GameMode::Puzzle
+        || game_state.state == RunningGameState::LevelSelect
+        || game_state.state == RunningGameState::Settings
+    {
+        return;
+    }
+
+    let mut back = keyboard_input.just_pressed(KeyCode::Escape);
+    for interaction in &mut button_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            back = true;
+        }
+    }
+
+    if !back {
+        return;
+    }
+
+    for entity in &mut scene_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+
+    game_events.send(RunningGameState::LevelSelect);
+}
+
+/// Shows the levels whenever the game goes back to choosing one
+fn show_level_select(
+    mut commands: Commands,
+    mut puzzle_state: ResMut<PuzzleState>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    game_events: Res<Events<RunningGameState>>,
+) {
+    let level_select = puzzle_state
+        .event_reader
+        .iter(&game_events)
+        .any(|event| *event == RunningGameState::LevelSelect);
+
+    if !level_select {
+        return;
+    }
+
+    // Levels are only loaded at startup when the game starts with puzzles
+    if puzzle_state.levels.is_empty() {
+        puzzle_state.load();
+    }
+
+    spawn_level_select(
+        &mut commands,
+        &puzzle_state,
+        &mut materials,
+        &asset_server,
+        *theme,
+    );
+}
+
+fn check_goal(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    grid: Res<Grid>,
+    session: Res<GameSession>,
+    hint_settings: Res<HintSettings>,
+    mut puzzle_state: ResMut<PuzzleState>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    mut game_events: ResMut<Events<RunningGameState>>,
+) {
+    if game_state.state != RunningGameState::Running
+        || puzzle_state.checked_moves == session.stats.moves
+    {
+        return;
+    }
+    puzzle_state.checked_moves = session.stats.moves;
+
+    let (goal_met, failed) = match puzzle_state.current_level() {
+        Some(level) => (
+            level.goal.is_met(&grid.board()),
+            level.is_failed(session.stats.moves),
+        ),
+        None => return,
+    };
+
+    if goal_met {
+        // Like the records of the other modes, edited and hinted solutions aren't the best result
+        if session.is_comparable() && !hint_settings.disqualifies(session.stats.hints) {
+            puzzle_state.record(session.stats.moves);
+        }
+        game_events.send(RunningGameState::Solved);
+        spawn_solved_screen(
+            &mut commands,
+            session.stats.moves,
+            &mut materials,
+            &asset_server,
+            *theme,
+        );
+    } else if failed {
+        game_events.send(RunningGameState::GameOver);
+    }
+}
+
+fn spawn_solved_screen(
+    commands: &mut Commands,
+    moves: u32,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    theme: Theme,
+) {
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect::all(Val::Percent(25.)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with(GameOverScreen)
+        .with_children(|parent| {
+            parent
+                .spawn(ButtonComponents {
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(10.0),
+                            ..Default::default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(15.)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with(LevelsButton)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextComponents {
+                            text: Text {
+                                value: String::new(),
+                                font: font_handle.clone(),
+                                style: TextStyle {
+                                    font_size: 25.,
+                                    color: Color::rgb(0., 0., 0.),
+                                },
+                            },
+                            ..Default::default()
+                        })
+                        .with(LocalizedText::new("levels"));
+                })
+                .spawn(TextComponents {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value: String::new(),
+                        font: font_handle.clone(),
+                        style: TextStyle {
+                            font_size: 40.0,
+                            color: theme.text(),
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(LocalizedText::new("solved").with_arg("moves", moves))
+                .with(Themed::Text);
+        });
+}
@@ -0,0 +1,379 @@
+//! Draws boards into images on the CPU, for board snapshots and exported replays
+//!
+//! Nothing in here touches the GPU or a window, so replays can be rendered on machines without
+//! either. The images follow the look of the game, but are drawn from the board alone, with the
+//! score labelled in English.
+
+use crate::rules::{Board, Tile, TileKind, Turn};
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use std::fmt;
+use std::str::FromStr;
+
+const CELL_SIZE: u32 = 100;
+const CELL_GAP: u32 = 12;
+/// Room above the board for the score
+const HEADER_HEIGHT: u32 = 60;
+const SCORE_FONT_SIZE: f32 = 32.0;
+const TILE_FONT_SIZE: f32 = 36.0;
+/// Scores up to 2^13 get their own hue, like the squares of the game
+const COLORED_SCORES: u32 = 14;
+/// Evenly spaced colors of the viridis color map, which goes from dark to light and can be told
+/// apart with every kind of color blindness
+const VIRIDIS: [[u8; 3]; 10] = [
+    [68, 1, 84],
+    [72, 40, 120],
+    [62, 73, 137],
+    [49, 104, 142],
+    [38, 130, 142],
+    [31, 158, 137],
+    [53, 183, 121],
+    [110, 206, 88],
+    [181, 222, 43],
+    [253, 231, 37],
+];
+
+/// The colors around the squares, which depend on the theme of the game
+#[derive(Clone, Copy, Debug)]
+pub struct Style {
+    pub background: Rgba<u8>,
+    pub text: Rgba<u8>,
+    pub cell: Rgba<u8>,
+    pub palette: Palette,
+}
+
+/// The colors of the squares by their score
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Palette {
+    /// A different hue for every score
+    Hue,
+    /// Darker to lighter with the score, to not rely on telling hues apart
+    Colorblind,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::Hue
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Palette, String> {
+        match s {
+            "hue" => Ok(Palette::Hue),
+            "colorblind" => Ok(Palette::Colorblind),
+            _ => Err(format!(
+                "unknown palette `{}`, expected hue or colorblind",
+                s
+            )),
+        }
+    }
+}
+
+/// Written as accepted by `--palette`
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Palette::Hue => "hue",
+            Palette::Colorblind => "colorblind",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Palette {
+    /// The color of a square, the same as in the game
+    pub fn color(self, tile: Tile) -> Rgba<u8> {
+        if tile.kind == TileKind::Wall {
+            return Rgba([89, 77, 77, 255]);
+        }
+
+        if !tile.score.is_power_of_two() || tile.score.trailing_zeros() >= COLORED_SCORES {
+            return Rgba([255, 255, 255, 255]);
+        }
+
+        let step = tile.score.trailing_zeros() as f32 / COLORED_SCORES as f32;
+        match self {
+            Palette::Hue => {
+                let rgb = bracket_color::prelude::HSV::from_f32(step, 0.8, 0.90).to_rgb();
+                Rgba([
+                    (rgb.r * 255.) as u8,
+                    (rgb.g * 255.) as u8,
+                    (rgb.b * 255.) as u8,
+                    255,
+                ])
+            }
+            Palette::Colorblind => viridis(step),
+        }
+    }
+
+    /// Black or white, whichever is easier to read on the square
+    pub fn label_color(self, tile: Tile) -> Rgba<u8> {
+        if luminance(self.color(tile)) > 0.4 {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        }
+    }
+}
+
+/// The color of a square in the default palette
+pub fn tile_color(tile: Tile) -> Rgba<u8> {
+    Palette::Hue.color(tile)
+}
+
+/// The color map at `t` from 0 to 1, between its neighbouring colors
+fn viridis(t: f32) -> Rgba<u8> {
+    let position = t.max(0.).min(1.) * (VIRIDIS.len() - 1) as f32;
+    let index = (position as usize).min(VIRIDIS.len() - 2);
+    let fraction = position - index as f32;
+    let (from, to) = (VIRIDIS[index], VIRIDIS[index + 1]);
+
+    let channel = |i: usize| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * fraction) as u8;
+    Rgba([channel(0), channel(1), channel(2), 255])
+}
+
+/// How bright a color looks, from 0 for black to 1 for white
+fn luminance(color: Rgba<u8>) -> f32 {
+    (0.2126 * color[0] as f32 + 0.7152 * color[1] as f32 + 0.0722 * color[2] as f32) / 255.
+}
+
+pub struct Renderer {
+    font: FontVec,
+    style: Style,
+}
+
+impl Renderer {
+    /// Uses the given TrueType font for the score and the squares
+    pub fn new(font_data: Vec<u8>, style: Style) -> Result<Renderer, String> {
+        let font = FontVec::try_from_vec(font_data).map_err(|err| err.to_string())?;
+        Ok(Renderer { font, style })
+    }
+
+    /// The width and height of the images of a board with the given size
+    pub fn image_size(&self, board_size: u32) -> (u32, u32) {
+        let width = board_size * CELL_SIZE + (board_size + 1) * CELL_GAP;
+        (width, width + HEADER_HEIGHT)
+    }
+
+    pub fn render(&self, board: &Board, score: u64) -> RgbaImage {
+        let squares: Vec<_> = board
+            .tiles()
+            .map(|((x, y), tile)| ((x as f32, y as f32), tile))
+            .collect();
+        self.draw(board.size(), score, &squares)
+    }
+
+    /// The squares of `before` part of the way through the turn, with `progress` from 0 to 1
+    ///
+    /// Merged squares slide on top of the square they merge into, new squares only show up on the
+    /// board after the turn.
+    pub fn render_turn(&self, before: &Board, turn: &Turn, progress: f32, score: u64) -> RgbaImage {
+        let moving: Vec<(u32, u32)> = turn
+            .moves
+            .iter()
+            .map(|square_move| square_move.from)
+            .collect();
+        let mut squares: Vec<_> = before
+            .tiles()
+            .filter(|(position, _)| !moving.contains(position))
+            .map(|((x, y), tile)| ((x as f32, y as f32), tile))
+            .collect();
+
+        for square_move in &turn.moves {
+            if let Some(tile) = before.tile(square_move.from) {
+                let lerp = |from: u32, to: u32| from as f32 + (to as f32 - from as f32) * progress;
+                let position = (
+                    lerp(square_move.from.0, square_move.to.0),
+                    lerp(square_move.from.1, square_move.to.1),
+                );
+                squares.push((position, tile));
+            }
+        }
+
+        self.draw(before.size(), score, &squares)
+    }
+
+    fn draw(&self, size: u32, score: u64, squares: &[((f32, f32), Tile)]) -> RgbaImage {
+        let (width, height) = self.image_size(size);
+        let mut image = RgbaImage::from_pixel(width, height, self.style.background);
+
+        self.draw_text(
+            &mut image,
+            &format!("Score {}", score),
+            SCORE_FONT_SIZE,
+            (width as f32 / 2., HEADER_HEIGHT as f32 / 2.),
+            self.style.text,
+        );
+
+        for x in 0..size {
+            for y in 0..size {
+                let origin = cell_origin(size, (x as f32, y as f32));
+                fill_rect(&mut image, origin, CELL_SIZE as f32, self.style.cell);
+            }
+        }
+
+        for &(position, tile) in squares {
+            let origin = cell_origin(size, position);
+            fill_rect(
+                &mut image,
+                origin,
+                CELL_SIZE as f32,
+                self.style.palette.color(tile),
+            );
+
+            let half = CELL_SIZE as f32 / 2.;
+            let label = match tile.kind {
+                TileKind::Wall => String::new(),
+                _ => tile.to_string(),
+            };
+            self.draw_text(
+                &mut image,
+                &label,
+                TILE_FONT_SIZE,
+                (origin.0 + half, origin.1 + half),
+                self.style.palette.label_color(tile),
+            );
+        }
+
+        image
+    }
+
+    /// Draws a single line of text centered on the given point
+    fn draw_text(
+        &self,
+        image: &mut RgbaImage,
+        text: &str,
+        font_size: f32,
+        center: (f32, f32),
+        color: Rgba<u8>,
+    ) {
+        let font = self.font.as_scaled(PxScale::from(font_size));
+        let width: f32 = text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum();
+        // The descent is negative, this puts the middle of the glyphs on the center
+        let baseline = center.1 + (font.ascent() + font.descent()) / 2.;
+        let mut x = center.0 - width / 2.;
+
+        for c in text.chars() {
+            let glyph_id = font.glyph_id(c);
+            let glyph = glyph_id.with_scale_and_position(font_size, point(x, baseline));
+            x += font.h_advance(glyph_id);
+
+            if let Some(outlined) = self.font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|glyph_x, glyph_y, coverage| {
+                    blend(
+                        image,
+                        bounds.min.x as i64 + glyph_x as i64,
+                        bounds.min.y as i64 + glyph_y as i64,
+                        color,
+                        coverage,
+                    );
+                });
+            }
+        }
+    }
+}
+
+/// The top left pixel of a cell, from the bottom left cell of the board at (0, 0)
+fn cell_origin(size: u32, (x, y): (f32, f32)) -> (f32, f32) {
+    let step = (CELL_SIZE + CELL_GAP) as f32;
+    let row = size as f32 - 1. - y;
+    (
+        CELL_GAP as f32 + x * step,
+        (HEADER_HEIGHT + CELL_GAP) as f32 + row * step,
+    )
+}
+
+fn fill_rect(image: &mut RgbaImage, (left, top): (f32, f32), size: f32, color: Rgba<u8>) {
+    for y in top.round() as i64..(top + size).round() as i64 {
+        for x in left.round() as i64..(left + size).round() as i64 {
+            blend(image, x, y, color, 1.);
+        }
+    }
+}
+
+/// Mixes the color into the pixel by its alpha times `coverage`, pixels outside are ignored
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+
+    let alpha = coverage.max(0.).min(1.) * color[3] as f32 / 255.;
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for channel in 0..3 {
+        let mixed = pixel[channel] as f32 * (1. - alpha) + color[channel] as f32 * alpha;
+        pixel[channel] = mixed.round() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: Rgba<u8> = Rgba([255, 211, 182, 255]);
+
+    fn renderer() -> Renderer {
+        let font = std::fs::read("assets/bungee_inline_regular.ttf").unwrap();
+        let style = Style {
+            background: BACKGROUND,
+            text: Rgba([76, 42, 44, 255]),
+            cell: Rgba([0, 0, 0, 51]),
+            palette: Palette::Hue,
+        };
+        Renderer::new(font, style).unwrap()
+    }
+
+    #[test]
+    fn squares_are_drawn_in_their_cell() {
+        let renderer = renderer();
+        let mut board = Board::new(4);
+        board.set((0, 0), Some(2));
+
+        let image = renderer.render(&board, 0);
+
+        assert_eq!(image.dimensions(), renderer.image_size(4));
+        // The bottom left corner of the bottom left cell, away from its label
+        let (left, top) = cell_origin(4, (0., 0.));
+        let corner = image.get_pixel(left as u32 + 2, top as u32 + 2);
+        assert_eq!(*corner, tile_color(Tile::normal(2)));
+        // Between the cells the background shows
+        assert_eq!(*image.get_pixel(2, image.height() - 2), BACKGROUND);
+    }
+
+    #[test]
+    fn sliding_squares_are_drawn_between_cells() {
+        let renderer = renderer();
+        let mut before = Board::new(4);
+        before.set((0, 0), Some(2));
+        let mut after = before.clone();
+        let turn = after.slide(crate::rules::MovementDirection::Right);
+
+        let image = renderer.render_turn(&before, &turn, 0.5, 0);
+
+        let (left, top) = cell_origin(4, (0.5, 0.));
+        let corner = image.get_pixel(left as u32 + 2, top as u32 + 2);
+        assert_eq!(*corner, tile_color(Tile::normal(2)));
+    }
+
+    #[test]
+    fn colorblind_squares_get_lighter_with_their_score() {
+        let scores = (0..COLORED_SCORES).map(|power| 1 << power);
+        let luminances: Vec<f32> = scores
+            .map(|score| luminance(Palette::Colorblind.color(Tile::normal(score))))
+            .collect();
+
+        assert!(luminances.windows(2).all(|pair| pair[0] < pair[1]));
+        // Labels stay readable on the darkest and the lightest square
+        assert_eq!(
+            Palette::Colorblind.label_color(Tile::normal(1)),
+            Rgba([255, 255, 255, 255])
+        );
+        assert_eq!(
+            Palette::Colorblind.label_color(Tile::normal(8192)),
+            Rgba([0, 0, 0, 255])
+        );
+    }
+}
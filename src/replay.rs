@@ -0,0 +1,164 @@
+use crate::animation::{AnimationKind, AnimationSettings};
+use crate::rules::MovementDirection;
+use crate::{GameMode, GameSession, GameState, MoveRequest, RunningGameState};
+use bevy::prelude::*;
+use std::fmt;
+use std::path::Path;
+
+/// Seconds between two moves of a replay, at the normal animation speed
+pub const REPLAY_MOVE_INTERVAL: f32 = 0.4;
+
+#[derive(Default)]
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(bevy::app::stage::PRE_UPDATE, play_replay.system());
+    }
+}
+
+/// The moves of a game, played back from the same seed
+pub struct Replay {
+    moves: Vec<MovementDirection>,
+    next: usize,
+    until_next_move: f32,
+}
+
+impl Replay {
+    pub fn new(moves: Vec<MovementDirection>) -> Replay {
+        Replay {
+            moves,
+            next: 0,
+            until_next_move: REPLAY_MOVE_INTERVAL,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.moves.len()
+    }
+
+    fn next_move(&mut self, delta_seconds: f32) -> Option<MovementDirection> {
+        self.until_next_move -= delta_seconds;
+        if self.until_next_move > 0. || self.is_finished() {
+            return None;
+        }
+
+        self.until_next_move += REPLAY_MOVE_INTERVAL;
+        self.next += 1;
+        Some(self.moves[self.next - 1])
+    }
+}
+
+/// A game written to a file, to be watched again with `--replay`
+///
+/// ```text
+/// mode: classic
+/// seed: 1234
+/// moves: LLURDR
+/// ```
+///
+/// Moves are written as the first letter of their direction. Replays always start from the usual
+/// board of their mode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ReplayFile {
+    pub mode: GameMode,
+    pub seed: u64,
+    pub moves: Vec<MovementDirection>,
+}
+
+impl ReplayFile {
+    pub fn parse(source: &str) -> Result<ReplayFile, String> {
+        let mut mode = None;
+        let mut seed = None;
+        let mut moves = vec![];
+
+        for (idx, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| format!("line {}: expected `key: value`", idx + 1))?
+                .trim();
+
+            match key {
+                "mode" => mode = Some(value.parse::<GameMode>()?),
+                "seed" => {
+                    seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("line {}: invalid seed `{}`", idx + 1, value))?,
+                    )
+                }
+                "moves" => {
+                    moves = value
+                        .chars()
+                        .map(|letter| match letter {
+                            'U' => Ok(MovementDirection::Up),
+                            'D' => Ok(MovementDirection::Down),
+                            'L' => Ok(MovementDirection::Left),
+                            'R' => Ok(MovementDirection::Right),
+                            _ => Err(format!("line {}: unknown move `{}`", idx + 1, letter)),
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                _ => return Err(format!("line {}: unknown key `{}`", idx + 1, key)),
+            }
+        }
+
+        Ok(ReplayFile {
+            mode: mode.ok_or("missing `mode`")?,
+            seed: seed.ok_or("missing `seed`")?,
+            moves,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<ReplayFile, String> {
+        let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ReplayFile::parse(&source)
+    }
+}
+
+impl fmt::Display for ReplayFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "mode: {}", self.mode)?;
+        writeln!(f, "seed: {}", self.seed)?;
+        write!(f, "moves: ")?;
+        for direction in &self.moves {
+            let letter = match direction {
+                MovementDirection::Up => 'U',
+                MovementDirection::Down => 'D',
+                MovementDirection::Left => 'L',
+                MovementDirection::Right => 'R',
+            };
+            write!(f, "{}", letter)?;
+        }
+        writeln!(f)
+    }
+}
+
+fn play_replay(
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    game_state: Res<GameState>,
+    mut session: ResMut<GameSession>,
+    mut move_requests: ResMut<Events<MoveRequest>>,
+) {
+    if game_state.state != RunningGameState::Running {
+        return;
+    }
+
+    // Moves keep pace with their animations, which play faster or slower with the time scale
+    let delta_seconds = settings.delta_seconds(AnimationKind::Sequence, time.delta_seconds);
+    if let Some(direction) = session
+        .replay
+        .as_mut()
+        .and_then(|replay| replay.next_move(delta_seconds))
+    {
+        move_requests.send(MoveRequest(direction));
+    }
+}
@@ -0,0 +1,661 @@
+//! The rules of the game, independent of any entities or rendering
+//!
+//! A [`Board`] only knows about the tiles in its cells, which makes it cheap to clone and lets
+//! anything that needs to reason about the game (puzzles, solvers, other frontends) share the
+//! exact same behaviour as the game itself.
+
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+use std::str::FromStr;
+
+/// The width and height of the board in every game mode
+pub const BOARD_SIZE: u32 = 4;
+/// One in how many randomly spawned squares is special, if special tiles are enabled
+const SPECIAL_TILE_ODDS: u32 = 12;
+
+/// The randomness of spawns, which a seed has to turn into the same squares on every platform
+///
+/// The algorithm of `StdRng` may change with any release of `rand`, this one is fixed.
+pub type SpawnRng = ChaCha8Rng;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum MovementDirection {
+    Up,
+    Down,
+    Right,
+    Left,
+}
+
+impl MovementDirection {
+    pub const ALL: [MovementDirection; 4] = [
+        MovementDirection::Up,
+        MovementDirection::Down,
+        MovementDirection::Right,
+        MovementDirection::Left,
+    ];
+
+    /// The neighbouring position in this direction, if it is still on a board of the given size
+    pub fn step(self, (x, y): (u32, u32), size: u32) -> Option<(u32, u32)> {
+        match self {
+            MovementDirection::Up if y + 1 < size => Some((x, y + 1)),
+            MovementDirection::Down if y > 0 => Some((x, y - 1)),
+            MovementDirection::Right if x + 1 < size => Some((x + 1, y)),
+            MovementDirection::Left if x > 0 => Some((x - 1, y)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TileKind {
+    Normal,
+    /// Never moves and never merges
+    Wall,
+    /// Merges with any other square, taking over its score
+    Wildcard,
+    /// Clears all neighbouring squares when merged
+    Bomb,
+    /// Doubles the score of the merge it is part of
+    Multiplier,
+}
+
+impl TileKind {
+    const SPECIAL: [TileKind; 3] = [TileKind::Wildcard, TileKind::Bomb, TileKind::Multiplier];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Tile {
+    pub kind: TileKind,
+    pub score: u64,
+}
+
+/// The outcome of two tiles merging
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Merge {
+    pub tile: Tile,
+    pub explodes: bool,
+}
+
+impl Tile {
+    pub fn normal(score: u64) -> Tile {
+        Tile {
+            kind: TileKind::Normal,
+            score,
+        }
+    }
+
+    pub fn wall() -> Tile {
+        Tile {
+            kind: TileKind::Wall,
+            score: 0,
+        }
+    }
+
+    pub fn wildcard() -> Tile {
+        Tile {
+            kind: TileKind::Wildcard,
+            score: 0,
+        }
+    }
+
+    pub fn merge_with(self, other: Tile) -> Option<Merge> {
+        let score = match (self.kind, other.kind) {
+            (TileKind::Wall, _) | (_, TileKind::Wall) => return None,
+            (TileKind::Wildcard, TileKind::Wildcard) => return None,
+            (TileKind::Wildcard, _) | (_, TileKind::Wildcard) => self.score.max(other.score) * 2,
+            _ if self.score == other.score => self.score + other.score,
+            _ => return None,
+        };
+
+        let has_kind = |kind| self.kind == kind || other.kind == kind;
+        let score = if has_kind(TileKind::Multiplier) {
+            score * 2
+        } else {
+            score
+        };
+
+        Some(Merge {
+            tile: Tile::normal(score),
+            explodes: has_kind(TileKind::Bomb),
+        })
+    }
+}
+
+/// Tiles are written as their score, prefixed with `b` for bombs and `x` for multipliers, or as
+/// `#` for walls and `*` for wildcards
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            TileKind::Normal => write!(f, "{}", self.score),
+            TileKind::Wall => write!(f, "#"),
+            TileKind::Wildcard => write!(f, "*"),
+            TileKind::Bomb => write!(f, "b{}", self.score),
+            TileKind::Multiplier => write!(f, "x{}", self.score),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseTileError(String);
+
+impl fmt::Display for ParseTileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid square `{}`", self.0)
+    }
+}
+
+impl FromStr for Tile {
+    type Err = ParseTileError;
+
+    fn from_str(s: &str) -> Result<Tile, ParseTileError> {
+        let error = || ParseTileError(s.to_string());
+        let parse_score = |score: &str| score.parse::<u64>().map_err(|_| error());
+
+        match s {
+            "#" => Ok(Tile::wall()),
+            "*" => Ok(Tile::wildcard()),
+            _ if s.starts_with('b') => Ok(Tile {
+                kind: TileKind::Bomb,
+                score: parse_score(&s[1..])?,
+            }),
+            _ if s.starts_with('x') => Ok(Tile {
+                kind: TileKind::Multiplier,
+                score: parse_score(&s[1..])?,
+            }),
+            _ => Ok(Tile::normal(parse_score(s)?)),
+        }
+    }
+}
+
+/// A single square moving one cell, possibly merging with the square that was there
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SquareMove {
+    pub from: (u32, u32),
+    pub to: (u32, u32),
+    pub merged: bool,
+    /// The square after the move
+    pub tile: Tile,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Spawn {
+    pub position: (u32, u32),
+    pub tile: Tile,
+}
+
+/// Everything that happened during a single turn
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Turn {
+    pub moves: Vec<SquareMove>,
+    /// Cells that were cleared by exploding bombs
+    pub cleared: Vec<(u32, u32)>,
+    pub spawn: Option<Spawn>,
+    /// No more moves are possible, the game is over
+    pub stuck: bool,
+}
+
+impl Turn {
+    pub fn moved(&self) -> bool {
+        !self.moves.is_empty()
+    }
+
+    pub fn points(&self) -> u64 {
+        self.moves
+            .iter()
+            .filter(|square_move| square_move.merged)
+            .map(|square_move| square_move.tile.score)
+            .sum()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Board {
+    size: u32,
+    cells: Vec<Option<Tile>>,
+}
+
+impl Board {
+    pub fn new(size: u32) -> Board {
+        Board {
+            size,
+            cells: vec![None; (size * size) as usize],
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    fn index(&self, (x, y): (u32, u32)) -> Option<usize> {
+        if x >= self.size || y >= self.size {
+            return None;
+        }
+        Some((x + self.size * y) as usize)
+    }
+
+    pub fn tile(&self, pos: (u32, u32)) -> Option<Tile> {
+        self.index(pos).and_then(|idx| self.cells[idx])
+    }
+
+    pub fn set_tile(&mut self, pos: (u32, u32), tile: Option<Tile>) {
+        let idx = self
+            .index(pos)
+            .unwrap_or_else(|| panic!("Tried to set a position outside of board: {:?}", pos));
+        self.cells[idx] = tile;
+    }
+
+    /// The score at the given position, walls and wildcards count as zero
+    pub fn get(&self, pos: (u32, u32)) -> Option<u64> {
+        self.tile(pos).map(|tile| tile.score)
+    }
+
+    /// Places a normal square with the given score
+    pub fn set(&mut self, pos: (u32, u32), score: Option<u64>) {
+        self.set_tile(pos, score.map(Tile::normal));
+    }
+
+    pub fn is_filled(&self, pos: (u32, u32)) -> bool {
+        self.tile(pos).is_some()
+    }
+
+    /// All filled cells with their tile
+    pub fn tiles(&self) -> impl Iterator<Item = ((u32, u32), Tile)> + '_ {
+        let size = self.size;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(move |(idx, tile)| {
+                tile.map(|tile| ((idx as u32 % size, idx as u32 / size), tile))
+            })
+    }
+
+    /// All squares that can be moved, so everything but walls
+    pub fn square_count(&self) -> usize {
+        self.tiles()
+            .filter(|(_, tile)| tile.kind != TileKind::Wall)
+            .count()
+    }
+
+    pub fn highest_score(&self) -> Option<u64> {
+        self.tiles().map(|(_, tile)| tile.score).max()
+    }
+
+    pub fn neighbor_positions(&self, pos: (u32, u32)) -> Vec<(u32, u32)> {
+        MovementDirection::ALL
+            .iter()
+            .filter_map(|direction| direction.step(pos, self.size))
+            .collect()
+    }
+
+    pub fn neighbors(&self, pos: (u32, u32)) -> Vec<Tile> {
+        self.neighbor_positions(pos)
+            .into_iter()
+            .filter_map(|pos| self.tile(pos))
+            .collect()
+    }
+
+    /// Whether any direction could still change the board
+    pub fn has_moves(&self) -> bool {
+        (0..self.size).any(|x| {
+            (0..self.size).any(|y| match self.tile((x, y)) {
+                None => true,
+                Some(tile) => self
+                    .neighbors((x, y))
+                    .into_iter()
+                    .any(|neighbor| tile.merge_with(neighbor).is_some()),
+            })
+        })
+    }
+
+    /// The scores that can be given to newly spawned squares
+    fn distinct_scores(&self) -> Vec<u64> {
+        let mut scores: Vec<u64> = self
+            .tiles()
+            .map(|(_, tile)| tile.score)
+            .filter(|&score| score > 0)
+            .collect();
+        scores.sort();
+        scores.dedup();
+        scores
+    }
+
+    /// Positions in the order they have to be moved in, the leading edge first
+    fn move_order(&self, direction: MovementDirection) -> Vec<(u32, u32)> {
+        let forward: Vec<u32> = (0..self.size).collect();
+        let backward: Vec<u32> = (0..self.size).rev().collect();
+
+        let (xs, ys) = match direction {
+            MovementDirection::Up | MovementDirection::Left => (&forward, &backward),
+            MovementDirection::Down | MovementDirection::Right => (&backward, &forward),
+        };
+
+        xs.iter()
+            .flat_map(|&x| ys.iter().map(move |&y| (x, y)))
+            .collect()
+    }
+
+    /// Moves every square by one cell in the given direction
+    ///
+    /// A square merges with the one in front of it if they can be merged, and squares that
+    /// have moved out of the way during this turn leave their cell free for the square behind.
+    /// Bombs explode once all squares have moved.
+    pub fn slide(&mut self, direction: MovementDirection) -> Turn {
+        let mut moves = vec![];
+        let mut explosions = vec![];
+
+        for pos in self.move_order(direction) {
+            let tile = match self.tile(pos) {
+                Some(tile) if tile.kind != TileKind::Wall => tile,
+                _ => continue,
+            };
+
+            let to = match direction.step(pos, self.size) {
+                Some(to) => to,
+                None => continue,
+            };
+
+            let (tile, merged) = match self.tile(to) {
+                None => (tile, false),
+                Some(other) => match tile.merge_with(other) {
+                    Some(merge) => {
+                        if merge.explodes {
+                            explosions.push(to);
+                        }
+                        (merge.tile, true)
+                    }
+                    None => continue,
+                },
+            };
+
+            self.set_tile(pos, None);
+            moves.push(SquareMove {
+                from: pos,
+                to,
+                merged,
+                tile,
+            });
+        }
+
+        for square_move in &moves {
+            self.set_tile(square_move.to, Some(square_move.tile));
+        }
+
+        let mut cleared = vec![];
+        for center in explosions {
+            for pos in self.neighbor_positions(center) {
+                match self.tile(pos) {
+                    Some(tile) if tile.kind != TileKind::Wall => {
+                        self.set_tile(pos, None);
+                        cleared.push(pos);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Turn {
+            moves,
+            cleared,
+            ..Default::default()
+        }
+    }
+
+    /// The free cells on the edge opposite to the movement, where new squares come in
+    pub fn spawn_slots(&self, direction: MovementDirection) -> Vec<(u32, u32)> {
+        let last = self.size - 1;
+        (0..self.size)
+            .map(|i| match direction {
+                MovementDirection::Up => (i, 0),
+                MovementDirection::Down => (i, last),
+                MovementDirection::Right => (0, i),
+                MovementDirection::Left => (last, i),
+            })
+            .filter(|&pos| !self.is_filled(pos))
+            .collect()
+    }
+
+    /// Plays a full turn: moving the squares, spawning a new one and checking for the end
+    pub fn play<R: Rng>(
+        &mut self,
+        direction: MovementDirection,
+        spawner: &mut Spawner,
+        rng: &mut R,
+    ) -> Turn {
+        let scores = self.distinct_scores();
+        let mut turn = self.slide(direction);
+
+        if turn.moved() {
+            turn.spawn = spawner.next(self, direction, &scores, rng);
+        }
+
+        if let Some(spawn) = turn.spawn {
+            self.set_tile(spawn.position, Some(spawn.tile));
+        }
+
+        turn.stuck = turn.spawn.is_none() && !self.has_moves();
+        turn
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum SpawnPolicy {
+    /// A random free edge cell gets one of the lower scores currently on the board, and every
+    /// so often a special tile if they are enabled
+    Random {
+        special_tiles: bool,
+    },
+    /// The given tiles are spawned in order on the first free edge cell, then nothing
+    Sequence(Vec<Tile>),
+    Disabled,
+}
+
+impl Default for SpawnPolicy {
+    fn default() -> SpawnPolicy {
+        SpawnPolicy::Random {
+            special_tiles: false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct Spawner {
+    pub policy: SpawnPolicy,
+    next: usize,
+}
+
+impl Spawner {
+    pub fn new(policy: SpawnPolicy) -> Spawner {
+        Spawner { policy, next: 0 }
+    }
+
+    /// Every spawn that may follow sliding `before` into `after`, all equally likely
+    ///
+    /// The rare special tiles are left out, and a sequence only offers its next tile.
+    pub fn possible_spawns(
+        &self,
+        before: &Board,
+        after: &Board,
+        direction: MovementDirection,
+    ) -> Vec<Spawn> {
+        match &self.policy {
+            SpawnPolicy::Disabled => vec![],
+            SpawnPolicy::Sequence(sequence) => sequence
+                .get(self.next)
+                .and_then(|&tile| {
+                    let position = *after.spawn_slots(direction).first()?;
+                    Some(Spawn { position, tile })
+                })
+                .into_iter()
+                .collect(),
+            SpawnPolicy::Random { .. } => {
+                let mut scores = before.distinct_scores();
+                scores.truncate((scores.len() / 3).max(1));
+
+                after
+                    .spawn_slots(direction)
+                    .into_iter()
+                    .flat_map(|position| {
+                        scores.iter().map(move |&score| Spawn {
+                            position,
+                            tile: Tile::normal(score),
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn next<R: Rng>(
+        &mut self,
+        board: &Board,
+        direction: MovementDirection,
+        scores: &[u64],
+        rng: &mut R,
+    ) -> Option<Spawn> {
+        match &self.policy {
+            SpawnPolicy::Disabled => None,
+            SpawnPolicy::Sequence(sequence) => {
+                let tile = *sequence.get(self.next)?;
+                let position = *board.spawn_slots(direction).first()?;
+                self.next += 1;
+
+                Some(Spawn { position, tile })
+            }
+            SpawnPolicy::Random { special_tiles } => {
+                let possible_coords = board.spawn_slots(direction);
+                let position = possible_coords[random_index(rng, possible_coords.len())?];
+
+                let choices = (scores.len() / 3).max(1).min(scores.len());
+                let score = scores[random_index(rng, choices)?];
+
+                let kind = if *special_tiles && rng.gen_ratio(1, SPECIAL_TILE_ODDS) {
+                    TileKind::SPECIAL[random_index(rng, TileKind::SPECIAL.len()).unwrap()]
+                } else {
+                    TileKind::Normal
+                };
+
+                let tile = match kind {
+                    TileKind::Wildcard => Tile::wildcard(),
+                    kind => Tile { kind, score },
+                };
+
+                Some(Spawn { position, tile })
+            }
+        }
+    }
+}
+
+/// A random index below `len`, drawn from a `u32` range as `usize` ones differ between platforms
+fn random_index<R: Rng>(rng: &mut R, len: usize) -> Option<usize> {
+    if len == 0 {
+        None
+    } else {
+        Some(rng.gen_range(0, len as u32) as usize)
+    }
+}
+
+/// How merges in a row build up a score multiplier
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct ComboRules {
+    /// How many merges in a row raise the multiplier by one
+    pub merges_per_step: u32,
+    pub max_multiplier: u64,
+}
+
+impl Default for ComboRules {
+    fn default() -> ComboRules {
+        ComboRules {
+            merges_per_step: 3,
+            max_multiplier: 5,
+        }
+    }
+}
+
+/// A chain of merges over one or more turns, which halves on every turn without a merge
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct Combo {
+    pub rules: Option<ComboRules>,
+    pub chain: u32,
+}
+
+impl Combo {
+    pub fn new(rules: Option<ComboRules>) -> Combo {
+        Combo { rules, chain: 0 }
+    }
+
+    /// Adds the merges of this turn to the chain, returns the multiplier for their scores
+    pub fn apply(&mut self, turn: &Turn) -> u64 {
+        if self.rules.is_none() || !turn.moved() {
+            return 1;
+        }
+
+        let merges = turn
+            .moves
+            .iter()
+            .filter(|square_move| square_move.merged)
+            .count() as u32;
+
+        if merges == 0 {
+            self.chain /= 2;
+            return 1;
+        }
+
+        self.chain += merges;
+        self.multiplier()
+    }
+
+    pub fn multiplier(&self) -> u64 {
+        match self.rules {
+            None => 1,
+            Some(rules) => {
+                let steps = self.chain.saturating_sub(1) / rules.merges_per_step.max(1);
+                (1 + steps as u64).min(rules.max_multiplier)
+            }
+        }
+    }
+}
+
+/// The board and rules a game (re)starts with
+pub struct StartingPosition {
+    pub board: Board,
+    pub spawn_policy: SpawnPolicy,
+    pub combo_rules: Option<ComboRules>,
+}
+
+impl StartingPosition {
+    pub fn classic() -> StartingPosition {
+        let mut board = Board::new(BOARD_SIZE);
+        board.set((1, 2), Some(1));
+        board.set((2, 1), Some(2));
+
+        StartingPosition {
+            board,
+            spawn_policy: SpawnPolicy::Random {
+                special_tiles: false,
+            },
+            combo_rules: None,
+        }
+    }
+
+    pub fn arcade() -> StartingPosition {
+        let mut board = StartingPosition::classic().board;
+        board.set_tile((3, 3), Some(Tile::wall()));
+
+        StartingPosition {
+            board,
+            spawn_policy: SpawnPolicy::Random {
+                special_tiles: true,
+            },
+            combo_rules: Some(ComboRules::default()),
+        }
+    }
+
+    pub fn empty() -> StartingPosition {
+        StartingPosition {
+            board: Board::new(BOARD_SIZE),
+            spawn_policy: SpawnPolicy::Disabled,
+            combo_rules: None,
+        }
+    }
+}
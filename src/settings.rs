@@ -0,0 +1,940 @@
+use crate::accessibility::Accessibility;
+use crate::animation::AnimationSettings;
+use crate::cli::{Options, MAX_TEXT_SCALE, MIN_TEXT_SCALE};
+use crate::effects::EffectsSettings;
+use crate::localization::{Localization, LocalizedText};
+use crate::theme::{Theme, Themed};
+use crate::{menu, storage, GameState, RunningGameState};
+use bevy::prelude::*;
+use bevy::render::pass::ClearColor;
+use bevy_squares::render::Palette;
+use std::fmt;
+use std::str::FromStr;
+
+/// The choices of the settings screen, kept between launches
+const SETTINGS_FILE: &str = "settings.txt";
+/// Written instead of a language code when the game follows the system
+const SYSTEM_LANGUAGE: &str = "system";
+const ROW_WIDTH: f32 = 440.;
+
+const MSAA_SAMPLES: [u32; 2] = [1, 4];
+const ANIMATION_SPEEDS: [f32; 5] = [0., 0.5, 1., 1.5, 2.];
+const TEXT_SCALES: [f32; 4] = [0.75, 1., 1.25, 1.5];
+const THEMES: [Theme; 3] = [Theme::Light, Theme::Dark, Theme::HighContrast];
+const PALETTES: [Palette; 2] = [Palette::Hue, Palette::Colorblind];
+
+/// Keys that moves and hints can be bound to, the function keys stay with the game
+const BINDABLE_KEYS: [KeyCode; 51] = [
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+];
+
+const ROWS: [(SettingsButton, &str); 20] = [
+    (SettingsButton::Vsync, "setting-vsync"),
+    (SettingsButton::Msaa, "setting-msaa"),
+    (SettingsButton::ShowFps, "setting-fps"),
+    (SettingsButton::Fullscreen, "setting-fullscreen"),
+    (SettingsButton::AnimationSpeed, "setting-animation-speed"),
+    (SettingsButton::Theme, "setting-theme"),
+    (SettingsButton::Language, "setting-language"),
+    (SettingsButton::Palette, "setting-palette"),
+    (SettingsButton::TileNumbers, "setting-tile-numbers"),
+    (SettingsButton::ReducedMotion, "setting-reduced-motion"),
+    (SettingsButton::TextScale, "setting-text-scale"),
+    (SettingsButton::Pop, "setting-pop"),
+    (SettingsButton::Particles, "setting-particles"),
+    (SettingsButton::ScreenShake, "setting-screen-shake"),
+    (SettingsButton::ScorePopups, "setting-score-popups"),
+    (SettingsButton::Key(Binding::Up), "setting-key-up"),
+    (SettingsButton::Key(Binding::Down), "setting-key-down"),
+    (SettingsButton::Key(Binding::Left), "setting-key-left"),
+    (SettingsButton::Key(Binding::Right), "setting-key-right"),
+    (SettingsButton::Key(Binding::Hint), "setting-key-hint"),
+];
+
+/// The settings screen, opened with F1 during a game or from the main menu
+///
+/// Changes apply as soon as they are made where the game allows it. The window is only set up at
+/// launch, so vsync, MSAA and fullscreen wait for a restart.
+#[derive(Default)]
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // The screen is closed before it is opened, so F1 doesn't do both in one frame
+        app.add_event::<OpenSettings>()
+            .init_resource::<SettingsState>()
+            .add_system(settings_screen_system.system())
+            .add_system(open_settings.system())
+            .add_system(update_setting_values.system())
+            .add_system(apply_settings.system());
+    }
+}
+
+/// Asks to show the settings screen, like the button of the main menu
+pub struct OpenSettings;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub vsync: bool,
+    /// 1 turns multisampling off
+    pub msaa_samples: u32,
+    pub show_fps: bool,
+    pub fullscreen: bool,
+    pub animation_speed: f32,
+    pub theme: Theme,
+    /// `None` follows the language of the system
+    pub language: Option<String>,
+    pub palette: Palette,
+    pub tile_numbers: bool,
+    pub reduced_motion: bool,
+    pub text_scale: f32,
+    pub effects: EffectsSettings,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            vsync: true,
+            msaa_samples: 1,
+            show_fps: true,
+            fullscreen: false,
+            animation_speed: 1.,
+            theme: Theme::default(),
+            language: None,
+            palette: Palette::default(),
+            tile_numbers: false,
+            reduced_motion: false,
+            text_scale: 1.,
+            effects: EffectsSettings::default(),
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// The saved settings, with the defaults for everything that isn't saved
+    pub fn load() -> Settings {
+        let contents = storage::data_file(SETTINGS_FILE)
+            .and_then(std::fs::read_to_string)
+            .unwrap_or_default();
+        Settings::parse(&contents)
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(storage::data_file(SETTINGS_FILE)?, self.to_string())
+    }
+
+    /// Every line holds a setting and its value, separated by a tab
+    fn parse(contents: &str) -> Settings {
+        let mut settings = Settings::default();
+        for line in contents.lines() {
+            let mut parts = line.splitn(2, '\t');
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                if let Err(err) = settings.set(name, value) {
+                    eprintln!("Ignoring the setting `{}`: {}", name, err);
+                }
+            }
+        }
+        settings
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "vsync" => self.vsync = parse_value(value)?,
+            "msaa_samples" => match parse_value::<u32>(value)? {
+                0 => return Err("at least one sample is needed".into()),
+                samples => self.msaa_samples = samples,
+            },
+            "show_fps" => self.show_fps = parse_value(value)?,
+            "fullscreen" => self.fullscreen = parse_value(value)?,
+            "animation_speed" => match parse_value::<f32>(value)? {
+                speed if speed.is_finite() && speed >= 0. => self.animation_speed = speed,
+                _ => return Err("the animation speed must be 0 or more".into()),
+            },
+            "theme" => self.theme = parse_value(value)?,
+            "language" if value == SYSTEM_LANGUAGE => self.language = None,
+            "language" => self.language = Some(value.to_string()),
+            "palette" => self.palette = parse_value(value)?,
+            "tile_numbers" => self.tile_numbers = parse_value(value)?,
+            "reduced_motion" => self.reduced_motion = parse_value(value)?,
+            "text_scale" => match parse_value::<f32>(value)? {
+                scale if (MIN_TEXT_SCALE..=MAX_TEXT_SCALE).contains(&scale) => {
+                    self.text_scale = scale
+                }
+                _ => {
+                    return Err(format!(
+                        "the text scale must be between {} and {}",
+                        MIN_TEXT_SCALE, MAX_TEXT_SCALE
+                    ))
+                }
+            },
+            "pop" => self.effects.pop = parse_value(value)?,
+            "particles" => self.effects.particles = parse_value(value)?,
+            "screen_shake" => self.effects.screen_shake = parse_value(value)?,
+            "score_popups" => self.effects.score_popups = parse_value(value)?,
+            _ => match Binding::ALL.iter().find(|binding| binding.name() == name) {
+                Some(&binding) => *self.key_bindings.key_mut(binding) = parse_key(value)?,
+                None => return Err("unknown setting".into()),
+            },
+        }
+        Ok(())
+    }
+
+    /// The settings in effect for this launch, with the command line overriding the saved ones
+    pub fn with_options(&self, options: &Options) -> Settings {
+        Settings {
+            theme: options.theme,
+            language: options.language.clone(),
+            palette: options.palette,
+            tile_numbers: options.tile_numbers,
+            reduced_motion: options.reduced_motion,
+            text_scale: options.text_scale,
+            animation_speed: options.animation_speed,
+            ..self.clone()
+        }
+    }
+
+    /// The settings to save, which keep the `saved` value of everything the command line
+    /// overrode at `launch` unless it was changed since
+    fn to_save(&self, launch: &Settings, saved: &Settings) -> Settings {
+        let mut to_save = self.clone();
+        if self.theme == launch.theme {
+            to_save.theme = saved.theme;
+        }
+        if self.language == launch.language {
+            to_save.language = saved.language.clone();
+        }
+        if self.palette == launch.palette {
+            to_save.palette = saved.palette;
+        }
+        if self.tile_numbers == launch.tile_numbers {
+            to_save.tile_numbers = saved.tile_numbers;
+        }
+        if self.reduced_motion == launch.reduced_motion {
+            to_save.reduced_motion = saved.reduced_motion;
+        }
+        if self.text_scale == launch.text_scale {
+            to_save.text_scale = saved.text_scale;
+        }
+        if self.animation_speed == launch.animation_speed {
+            to_save.animation_speed = saved.animation_speed;
+        }
+        to_save
+    }
+
+    /// The options to launch with, which the command line can still override
+    pub fn launch_options(&self) -> Options {
+        Options {
+            theme: self.theme,
+            language: self.language.clone(),
+            palette: self.palette,
+            tile_numbers: self.tile_numbers,
+            reduced_motion: self.reduced_motion,
+            text_scale: self.text_scale,
+            animation_speed: self.animation_speed,
+            ..Options::default()
+        }
+    }
+}
+
+/// Written as read by `Settings::load`
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "vsync\t{}", self.vsync)?;
+        writeln!(f, "msaa_samples\t{}", self.msaa_samples)?;
+        writeln!(f, "show_fps\t{}", self.show_fps)?;
+        writeln!(f, "fullscreen\t{}", self.fullscreen)?;
+        writeln!(f, "animation_speed\t{}", self.animation_speed)?;
+        writeln!(f, "theme\t{}", self.theme)?;
+        writeln!(
+            f,
+            "language\t{}",
+            self.language.as_deref().unwrap_or(SYSTEM_LANGUAGE)
+        )?;
+        writeln!(f, "palette\t{}", self.palette)?;
+        writeln!(f, "tile_numbers\t{}", self.tile_numbers)?;
+        writeln!(f, "reduced_motion\t{}", self.reduced_motion)?;
+        writeln!(f, "text_scale\t{}", self.text_scale)?;
+        writeln!(f, "pop\t{}", self.effects.pop)?;
+        writeln!(f, "particles\t{}", self.effects.particles)?;
+        writeln!(f, "screen_shake\t{}", self.effects.screen_shake)?;
+        writeln!(f, "score_popups\t{}", self.effects.score_popups)?;
+        for &binding in Binding::ALL.iter() {
+            writeln!(
+                f,
+                "{}\t{:?}",
+                binding.name(),
+                self.key_bindings.key(binding)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_value<T>(value: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|err| format!("invalid value `{}`: {}", value, err))
+}
+
+fn parse_key(name: &str) -> Result<KeyCode, String> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|key| format!("{:?}", key) == name)
+        .copied()
+        .ok_or_else(|| format!("`{}` can't be bound", name))
+}
+
+/// The keys that move the squares and ask for a hint
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub hint: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            up: KeyCode::Up,
+            down: KeyCode::Down,
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            hint: KeyCode::H,
+        }
+    }
+}
+
+impl KeyBindings {
+    fn key(&self, binding: Binding) -> KeyCode {
+        match binding {
+            Binding::Up => self.up,
+            Binding::Down => self.down,
+            Binding::Left => self.left,
+            Binding::Right => self.right,
+            Binding::Hint => self.hint,
+        }
+    }
+
+    fn key_mut(&mut self, binding: Binding) -> &mut KeyCode {
+        match binding {
+            Binding::Up => &mut self.up,
+            Binding::Down => &mut self.down,
+            Binding::Left => &mut self.left,
+            Binding::Right => &mut self.right,
+            Binding::Hint => &mut self.hint,
+        }
+    }
+
+    /// Binds the key, swapping keys with the binding that had it so every key does one thing
+    fn rebind(&mut self, binding: Binding, key: KeyCode) {
+        let old_key = self.key(binding);
+        if let Some(&other) = Binding::ALL.iter().find(|other| self.key(**other) == key) {
+            *self.key_mut(other) = old_key;
+        }
+        *self.key_mut(binding) = key;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Binding {
+    Up,
+    Down,
+    Left,
+    Right,
+    Hint,
+}
+
+impl Binding {
+    const ALL: [Binding; 5] = [
+        Binding::Up,
+        Binding::Down,
+        Binding::Left,
+        Binding::Right,
+        Binding::Hint,
+    ];
+
+    /// The name of the binding in the settings file
+    fn name(self) -> &'static str {
+        match self {
+            Binding::Up => "key_up",
+            Binding::Down => "key_down",
+            Binding::Left => "key_left",
+            Binding::Right => "key_right",
+            Binding::Hint => "key_hint",
+        }
+    }
+}
+
+#[derive(Default)]
+struct SettingsState {
+    open_reader: EventReader<OpenSettings>,
+    /// The state to go back to, while the screen is open
+    previous: Option<RunningGameState>,
+    /// The button that was clicked last frame, as buttons stay clicked while held
+    held: Option<SettingsButton>,
+    /// The binding waiting for its new key
+    rebinding: Option<Binding>,
+    /// The settings the game follows right now, to only apply what changed
+    applied: Option<Settings>,
+    /// The settings the game was launched with, including the command line options
+    launch: Option<Settings>,
+}
+
+struct SettingsScreen;
+
+/// Clicking a setting switches it to its next value
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SettingsButton {
+    Vsync,
+    Msaa,
+    ShowFps,
+    Fullscreen,
+    AnimationSpeed,
+    Theme,
+    Language,
+    Palette,
+    TileNumbers,
+    ReducedMotion,
+    TextScale,
+    Pop,
+    Particles,
+    ScreenShake,
+    ScorePopups,
+    Key(Binding),
+    Back,
+}
+
+impl SettingsButton {
+    fn value(self, settings: &Settings, rebinding: Option<Binding>) -> LocalizedText {
+        match self {
+            SettingsButton::Vsync => on_off(settings.vsync),
+            SettingsButton::Msaa if settings.msaa_samples <= 1 => on_off(false),
+            SettingsButton::Msaa => {
+                LocalizedText::new("samples").with_arg("samples", settings.msaa_samples)
+            }
+            SettingsButton::ShowFps => on_off(settings.show_fps),
+            SettingsButton::Fullscreen => on_off(settings.fullscreen),
+            SettingsButton::AnimationSpeed if settings.animation_speed <= 0. => on_off(false),
+            SettingsButton::AnimationSpeed => {
+                LocalizedText::new("speed").with_arg("speed", settings.animation_speed)
+            }
+            SettingsButton::Theme => LocalizedText::new(match settings.theme {
+                Theme::Light => "theme-light",
+                Theme::Dark => "theme-dark",
+                Theme::HighContrast => "theme-high-contrast",
+            }),
+            SettingsButton::Language => match &settings.language {
+                Some(language) => LocalizedText::verbatim(language),
+                None => LocalizedText::new("language-system"),
+            },
+            SettingsButton::Palette => LocalizedText::new(match settings.palette {
+                Palette::Hue => "palette-hue",
+                Palette::Colorblind => "palette-colorblind",
+            }),
+            SettingsButton::TileNumbers => on_off(settings.tile_numbers),
+            SettingsButton::ReducedMotion => on_off(settings.reduced_motion),
+            SettingsButton::TextScale => percent(settings.text_scale),
+            SettingsButton::Pop => on_off(settings.effects.pop),
+            SettingsButton::Particles => on_off(settings.effects.particles),
+            SettingsButton::ScreenShake => on_off(settings.effects.screen_shake),
+            SettingsButton::ScorePopups => on_off(settings.effects.score_popups),
+            SettingsButton::Key(binding) if rebinding == Some(binding) => {
+                LocalizedText::new("press-key")
+            }
+            SettingsButton::Key(binding) => {
+                LocalizedText::verbatim(format!("{:?}", settings.key_bindings.key(binding)))
+            }
+            SettingsButton::Back => LocalizedText::new("back"),
+        }
+    }
+
+    /// Switches the setting to its next value, languages are only known once loaded
+    fn change(self, settings: &mut Settings, languages: &[String]) {
+        match self {
+            SettingsButton::Vsync => settings.vsync = !settings.vsync,
+            SettingsButton::Msaa => {
+                settings.msaa_samples = next(&MSAA_SAMPLES, &settings.msaa_samples)
+            }
+            SettingsButton::ShowFps => settings.show_fps = !settings.show_fps,
+            SettingsButton::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            SettingsButton::AnimationSpeed => {
+                settings.animation_speed = next(&ANIMATION_SPEEDS, &settings.animation_speed)
+            }
+            SettingsButton::Theme => settings.theme = next(&THEMES, &settings.theme),
+            SettingsButton::Language => {
+                let choices: Vec<Option<String>> = std::iter::once(None)
+                    .chain(languages.iter().cloned().map(Some))
+                    .collect();
+                settings.language = next(&choices, &settings.language);
+            }
+            SettingsButton::Palette => settings.palette = next(&PALETTES, &settings.palette),
+            SettingsButton::TileNumbers => settings.tile_numbers = !settings.tile_numbers,
+            SettingsButton::ReducedMotion => settings.reduced_motion = !settings.reduced_motion,
+            SettingsButton::TextScale => {
+                settings.text_scale = next(&TEXT_SCALES, &settings.text_scale)
+            }
+            SettingsButton::Pop => settings.effects.pop = !settings.effects.pop,
+            SettingsButton::Particles => settings.effects.particles = !settings.effects.particles,
+            SettingsButton::ScreenShake => {
+                settings.effects.screen_shake = !settings.effects.screen_shake
+            }
+            SettingsButton::ScorePopups => {
+                settings.effects.score_popups = !settings.effects.score_popups
+            }
+            SettingsButton::Key(_) | SettingsButton::Back => (),
+        }
+    }
+}
+
+fn on_off(on: bool) -> LocalizedText {
+    LocalizedText::new(if on { "on" } else { "off" })
+}
+
+fn percent(value: f32) -> LocalizedText {
+    LocalizedText::new("percent").with_arg("percent", (value * 100.).round())
+}
+
+/// The value after `current`, or the first one if `current` isn't one of them
+fn next<T: Clone + PartialEq>(values: &[T], current: &T) -> T {
+    let index = values
+        .iter()
+        .position(|value| value == current)
+        .map_or(0, |index| (index + 1) % values.len());
+    values[index].clone()
+}
+
+/// Shows the value of a setting next to its name
+struct SettingValue(SettingsButton);
+
+fn spawn_settings_screen(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    theme: Theme,
+) {
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+    let button_material = materials.add(Color::rgba(1., 1., 1., 0.6).into());
+    let button_text = |font_size: f32| TextComponents {
+        text: Text {
+            value: String::new(),
+            font: font_handle.clone(),
+            style: TextStyle {
+                font_size,
+                color: Color::rgb(0., 0., 0.),
+            },
+        },
+        ..Default::default()
+    };
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect::all(Val::Px(0.)),
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(theme.background().into()),
+            ..Default::default()
+        })
+        .with(SettingsScreen)
+        .with(Themed::Background)
+        .with_children(|parent| {
+            parent
+                .spawn(TextComponents {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.0)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value: String::new(),
+                        font: font_handle.clone(),
+                        style: TextStyle {
+                            font_size: 30.0,
+                            color: theme.text(),
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(LocalizedText::new("settings"))
+                .with(Themed::Text);
+
+            for &(button, key) in ROWS.iter() {
+                parent
+                    .spawn(ButtonComponents {
+                        style: Style {
+                            size: Size::new(Val::Px(ROW_WIDTH), Val::Auto),
+                            margin: Rect::all(Val::Px(1.0)),
+                            justify_content: JustifyContent::SpaceBetween,
+                            align_items: AlignItems::Center,
+                            padding: Rect::all(Val::Px(3.)),
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .with(button)
+                    .with_children(|parent| {
+                        parent
+                            .spawn(button_text(16.))
+                            .with(LocalizedText::new(key))
+                            .spawn(button_text(16.))
+                            .with(SettingValue(button))
+                            .with(LocalizedText::empty());
+                    });
+            }
+
+            parent
+                .spawn(TextComponents {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value: String::new(),
+                        font: font_handle.clone(),
+                        style: TextStyle {
+                            font_size: 12.0,
+                            color: theme.accent(),
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(LocalizedText::new("settings-restart-note"))
+                .with(Themed::Accent)
+                .spawn(ButtonComponents {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(10.)),
+                        ..Default::default()
+                    },
+                    material: button_material.clone(),
+                    ..Default::default()
+                })
+                .with(SettingsButton::Back)
+                .with_children(|parent| {
+                    parent
+                        .spawn(button_text(20.))
+                        .with(LocalizedText::new("back"));
+                });
+        });
+}
+
+fn open_settings(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    game_state: Res<GameState>,
+    mut state: ResMut<SettingsState>,
+    open_events: Res<Events<OpenSettings>>,
+    mut game_events: ResMut<Events<RunningGameState>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+) {
+    let requested = state.open_reader.iter(&open_events).count() > 0;
+    let shortcut =
+        keyboard_input.just_pressed(KeyCode::F1) && game_state.state == RunningGameState::Running;
+
+    if !(requested || shortcut) || state.previous.is_some() {
+        return;
+    }
+
+    state.previous = Some(game_state.state);
+    game_events.send(RunningGameState::Settings);
+    spawn_settings_screen(&mut commands, &mut materials, &asset_server, *theme);
+}
+
+fn settings_screen_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    localization: Res<Localization>,
+    mut settings: ResMut<Settings>,
+    mut state: ResMut<SettingsState>,
+    mut game_events: ResMut<Events<RunningGameState>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    mut button_query: Query<(&Interaction, &SettingsButton)>,
+    mut screen_query: Query<With<SettingsScreen, Entity>>,
+) {
+    let previous = match state.previous {
+        Some(previous) => previous,
+        None => return,
+    };
+
+    let mut clicked = None;
+    for (interaction, button) in &mut button_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            clicked = Some(*button);
+        }
+    }
+    let pressed = if clicked != state.held { clicked } else { None };
+    state.held = clicked;
+
+    if let Some(binding) = state.rebinding {
+        // Escape keeps the old key
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            state.rebinding = None;
+        } else if let Some(&key) = keyboard_input
+            .get_just_pressed()
+            .find(|key| BINDABLE_KEYS.contains(*key))
+        {
+            settings.key_bindings.rebind(binding, key);
+            state.rebinding = None;
+        }
+        return;
+    }
+
+    let close = keyboard_input.just_pressed(KeyCode::Escape)
+        || keyboard_input.just_pressed(KeyCode::F1)
+        || pressed == Some(SettingsButton::Back);
+
+    match pressed {
+        _ if close => (),
+        Some(SettingsButton::Key(binding)) => state.rebinding = Some(binding),
+        Some(button) => button.change(&mut settings, &localization.languages()),
+        None => (),
+    }
+
+    if !close {
+        return;
+    }
+
+    for entity in &mut screen_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+    // Command line options only last for this launch
+    let to_save = match &state.launch {
+        Some(launch) => settings.to_save(launch, &Settings::load()),
+        None => settings.clone(),
+    };
+    if let Err(err) = to_save.save() {
+        eprintln!("Could not save the settings: {}", err);
+    }
+
+    state.previous = None;
+    game_events.send(previous);
+    if previous == RunningGameState::MainMenu {
+        menu::spawn_main_menu(&mut commands, &mut materials, &asset_server, *theme);
+    }
+}
+
+fn update_setting_values(
+    settings: Res<Settings>,
+    state: Res<SettingsState>,
+    mut value_query: Query<(&SettingValue, &mut LocalizedText)>,
+) {
+    for (value, mut text) in &mut value_query.iter() {
+        *text = value.0.value(&settings, state.rebinding);
+    }
+}
+
+/// Hands the settings that changed over to the resources the game follows
+fn apply_settings(
+    settings: Res<Settings>,
+    mut state: ResMut<SettingsState>,
+    mut animation: ResMut<AnimationSettings>,
+    mut accessibility: ResMut<Accessibility>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut effects: ResMut<EffectsSettings>,
+    mut localization: ResMut<Localization>,
+    mut theme: ResMut<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut material_query: Query<(&Themed, &Handle<ColorMaterial>)>,
+    mut text_query: Query<(&Themed, &mut Text)>,
+) {
+    // The game starts out with the settings, which already include the command line options
+    let applied = match &state.applied {
+        Some(applied) if *applied == *settings => return,
+        Some(applied) => applied.clone(),
+        None => {
+            state.applied = Some(settings.clone());
+            state.launch = Some(settings.clone());
+            return;
+        }
+    };
+
+    if settings.animation_speed != applied.animation_speed {
+        animation.time_scale = settings.animation_speed;
+    }
+    if settings.reduced_motion != applied.reduced_motion {
+        animation.reduced_motion = settings.reduced_motion;
+    }
+    if settings.palette != applied.palette {
+        accessibility.palette = settings.palette;
+    }
+    if settings.tile_numbers != applied.tile_numbers {
+        accessibility.tile_numbers = settings.tile_numbers;
+    }
+    if settings.text_scale != applied.text_scale {
+        accessibility.text_scale = settings.text_scale;
+    }
+    if settings.effects != applied.effects {
+        *effects = settings.effects;
+    }
+    if settings.key_bindings != applied.key_bindings {
+        *key_bindings = settings.key_bindings;
+    }
+    if settings.language != applied.language {
+        localization.set_language(settings.language.clone());
+    }
+    if settings.theme != applied.theme {
+        // Only what is tagged with its part of the theme follows, everything else keeps its colors
+        *theme = settings.theme;
+        clear_color.0 = theme.background();
+
+        for (themed, handle) in &mut material_query.iter() {
+            if let Some(material) = materials.get_mut(&handle) {
+                material.color = themed.color(*theme);
+            }
+        }
+        for (themed, mut text) in &mut text_query.iter() {
+            text.style.color = themed.color(*theme);
+        }
+    }
+
+    state.applied = Some(settings.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_saved_and_loaded_again() {
+        let mut settings = Settings {
+            vsync: false,
+            msaa_samples: 4,
+            fullscreen: true,
+            animation_speed: 1.5,
+            theme: Theme::HighContrast,
+            language: Some("de".to_string()),
+            palette: Palette::Colorblind,
+            text_scale: 1.25,
+            ..Settings::default()
+        };
+        settings.effects.screen_shake = false;
+        settings.key_bindings.up = KeyCode::W;
+
+        assert_eq!(Settings::parse(&settings.to_string()), settings);
+        assert_eq!(
+            Settings::parse(&Settings::default().to_string()),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn invalid_settings_keep_their_default() {
+        let settings = Settings::parse(
+            "vsync\tsometimes\ntext_scale\t9\nanimation_speed\tNaN\nkey_up\tF1\ncolor\tblue\n\
+             show_fps\tfalse\n",
+        );
+
+        assert_eq!(
+            settings,
+            Settings {
+                show_fps: false,
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn command_line_options_are_not_saved() {
+        let saved = Settings::default();
+        let options = Options {
+            theme: Theme::Dark,
+            text_scale: 1.5,
+            ..saved.launch_options()
+        };
+        let launch = saved.with_options(&options);
+        let changed = Settings {
+            text_scale: 1.25,
+            ..launch.clone()
+        };
+
+        assert_eq!(launch.theme, Theme::Dark);
+        assert_eq!(
+            changed.to_save(&launch, &saved),
+            Settings {
+                text_scale: 1.25,
+                ..saved
+            }
+        );
+    }
+
+    #[test]
+    fn rebinding_a_used_key_swaps_the_bindings() {
+        let mut bindings = KeyBindings::default();
+        bindings.rebind(Binding::Up, KeyCode::Down);
+
+        assert_eq!(bindings.up, KeyCode::Down);
+        assert_eq!(bindings.down, KeyCode::Up);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::accessibility::Accessibility;
+use crate::replay::{ReplayFile, REPLAY_MOVE_INTERVAL};
+use crate::rules::{Combo, SpawnRng, Spawner, StartingPosition};
+use crate::theme::Theme;
+use crate::{storage, GameMode, Grid, ScoreState, SLIDE_DURATION};
+use bevy::prelude::*;
+use bevy_squares::render::{Renderer, Style};
+use image::{gif::GifEncoder, Delay, Frame, RgbaImage};
+use rand::SeedableRng;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FONT_FILE: &str = "assets/bungee_inline_regular.ttf";
+/// Images drawn for every move of an exported replay, while the squares slide
+const SLIDE_FRAMES: u32 = 5;
+/// How long the end of an exported replay stays on screen before it loops
+const FINAL_FRAME_MS: u32 = 2000;
+
+/// F12 saves a snapshot of the board to the data directory
+///
+/// This version of bevy can't read back what the window shows, so this is not a screenshot: the
+/// board and score are drawn again on the CPU by `bevy_squares::render`. Effects, menus and the
+/// rest of the interface are left out, and the score is always labelled in English.
+#[derive(Default)]
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(board_snapshot_system.system());
+    }
+}
+
+fn renderer(style: Style) -> Result<Renderer, String> {
+    let font = std::fs::read(FONT_FILE).map_err(|err| format!("{}: {}", FONT_FILE, err))?;
+    Renderer::new(font, style)
+}
+
+fn board_snapshot_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    theme: Res<Theme>,
+    accessibility: Res<Accessibility>,
+    grid: Res<Grid>,
+    score: Res<ScoreState>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let style = theme.render_style(accessibility.palette);
+    let result = renderer(style).and_then(|renderer| {
+        let path =
+            storage::data_file(&format!("board-{}.png", seconds)).map_err(|err| err.to_string())?;
+        renderer
+            .render(&grid.board(), score.score)
+            .save(&path)
+            .map_err(|err| err.to_string())?;
+        Ok(path)
+    });
+
+    match result {
+        Ok(path) => println!("Saved a board snapshot to {}", path.display()),
+        Err(err) => eprintln!("Could not save the board snapshot: {}", err),
+    }
+}
+
+/// Plays the replay with the rules alone and writes a board snapshot of every step into an
+/// animated GIF
+///
+/// The moves are timed like when watching the replay in the game, but like F12 the frames only
+/// show the board and score instead of what the game draws. No window is needed, so this also
+/// works on machines without a GPU.
+pub fn export_replay(replay: &ReplayFile, style: Style, path: &Path) -> Result<(), String> {
+    let starting_position = match replay.mode {
+        GameMode::Classic | GameMode::Daily => StartingPosition::classic(),
+        GameMode::Arcade => StartingPosition::arcade(),
+        GameMode::Puzzle => return Err("replays of puzzles can not be exported".into()),
+    };
+    let renderer = renderer(style)?;
+    let mut rng = SpawnRng::seed_from_u64(replay.seed);
+    let mut spawner = Spawner::new(starting_position.spawn_policy);
+    let mut combo = Combo::new(starting_position.combo_rules);
+    let mut board = starting_position.board;
+    let mut score = 0;
+
+    let slide_delay = (SLIDE_DURATION * 1000.) as u32 / SLIDE_FRAMES;
+    let pause_delay = (REPLAY_MOVE_INTERVAL * 1000.) as u32 - slide_delay * SLIDE_FRAMES;
+    let frame = |image: RgbaImage, delay_ms: u32| {
+        Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+    };
+
+    let mut frames = vec![frame(renderer.render(&board, score), pause_delay)];
+    for (index, &direction) in replay.moves.iter().enumerate() {
+        let before = board.clone();
+        let turn = board.play(direction, &mut spawner, &mut rng);
+
+        for step in 1..SLIDE_FRAMES {
+            let progress = step as f32 / SLIDE_FRAMES as f32;
+            frames.push(frame(
+                renderer.render_turn(&before, &turn, progress, score),
+                slide_delay,
+            ));
+        }
+
+        score += turn.points() * combo.apply(&turn);
+        let is_last = index + 1 == replay.moves.len();
+        frames.push(frame(
+            renderer.render(&board, score),
+            if is_last { FINAL_FRAME_MS } else { pause_delay },
+        ));
+    }
+
+    let file = std::fs::File::create(path).map_err(|err| err.to_string())?;
+    GifEncoder::new(file)
+        .encode_frames(frames)
+        .map_err(|err| err.to_string())
+}
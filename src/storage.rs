@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+/// Directory in which all locally persisted game data lives
+///
+/// Follows `$XDG_DATA_HOME` if set, otherwise `~/.local/share`, falling back to the working
+/// directory if neither is available.
+pub fn data_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    base.join("bevy_squares")
+}
+
+/// Path of a file inside the data directory, creating the directory if needed
+pub fn data_file(name: &str) -> std::io::Result<PathBuf> {
+    let dir = data_dir();
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(name))
+}
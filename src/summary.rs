@@ -0,0 +1,454 @@
+use crate::animation::{self, Easing, Tween};
+use crate::daily::DailyChallenge;
+use crate::hint::HintSettings;
+use crate::localization::LocalizedText;
+use crate::replay::ReplayFile;
+use crate::theme::{Theme, Themed};
+use crate::{
+    calculate_grid_position, menu, storage, GameMode, GameOverScreen, GameSession, GameState, Grid,
+    RestartButton, RestartGame, RunningGameState, ScoreState, GRID_SIZE, SQUARE_MARGIN,
+    SQUARE_WIDTH,
+};
+use bevy::prelude::*;
+
+const RECORDS_FILE: &str = "records.txt";
+/// The last finished game, which can be watched again with `--replay`
+const LAST_REPLAY_FILE: &str = "last_replay.txt";
+/// How long the board takes to fade out once the game is over
+const FADE_DURATION: f32 = 0.6;
+
+#[derive(Default)]
+pub struct SummaryPlugin;
+
+impl Plugin for SummaryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SummaryState>()
+            .add_system(track_play_time.system())
+            // Before the summary, which shows the records
+            .add_system(update_records.system())
+            .add_system(show_summary.system())
+            .add_system(save_last_replay.system())
+            .add_system(summary_button_system.system());
+    }
+}
+
+#[derive(Default)]
+struct SummaryState {
+    event_reader: EventReader<RunningGameState>,
+    records_reader: EventReader<RunningGameState>,
+    replay_reader: EventReader<RunningGameState>,
+    /// The records before and after the last finished game, the same if it wasn't saved, none for
+    /// puzzles
+    records: Option<(Records, Records)>,
+}
+
+struct ReplayButton;
+struct MenuButton;
+
+/// The best results ever reached in a game mode
+#[derive(Default, Clone, Copy)]
+struct Records {
+    best_score: u64,
+    highest_tile: u64,
+}
+
+impl Records {
+    fn parse(line: &str) -> Option<(String, Records)> {
+        let mut parts = line.split('\t');
+        let mode = parts.next()?.to_string();
+        let best_score = parts.next()?.parse().ok()?;
+        let highest_tile = parts.next()?.parse().ok()?;
+
+        Some((
+            mode,
+            Records {
+                best_score,
+                highest_tile,
+            },
+        ))
+    }
+}
+
+fn load_all_records() -> Vec<(String, Records)> {
+    storage::data_file(RECORDS_FILE)
+        .and_then(std::fs::read_to_string)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(Records::parse)
+        .collect()
+}
+
+fn load_records(game_mode: GameMode) -> Records {
+    let mode = format!("{:?}", game_mode);
+    load_all_records()
+        .into_iter()
+        .find(|(entry_mode, _)| *entry_mode == mode)
+        .map(|(_, records)| records)
+        .unwrap_or_default()
+}
+
+fn save_records(game_mode: GameMode, records: Records) -> std::io::Result<()> {
+    let mode = format!("{:?}", game_mode);
+    let mut contents = String::new();
+    for (entry_mode, entry) in load_all_records()
+        .into_iter()
+        .filter(|(entry_mode, _)| *entry_mode != mode)
+        .chain(std::iter::once((mode.clone(), records)))
+    {
+        contents += &format!(
+            "{}\t{}\t{}\n",
+            entry_mode, entry.best_score, entry.highest_tile
+        );
+    }
+
+    std::fs::write(storage::data_file(RECORDS_FILE)?, contents)
+}
+
+fn track_play_time(time: Res<Time>, game_state: Res<GameState>, mut session: ResMut<GameSession>) {
+    if game_state.state == RunningGameState::Running {
+        session.stats.play_time += time.delta_seconds;
+    }
+}
+
+/// Updates the records of the mode once a game is over
+fn update_records(
+    game_mode: Res<GameMode>,
+    score: Res<ScoreState>,
+    grid: Res<Grid>,
+    session: Res<GameSession>,
+    hint_settings: Res<HintSettings>,
+    mut summary_state: ResMut<SummaryState>,
+    game_events: Res<Events<RunningGameState>>,
+) {
+    let game_over = summary_state
+        .records_reader
+        .iter(&game_events)
+        .any(|event| *event == RunningGameState::GameOver);
+
+    if !game_over {
+        return;
+    }
+
+    // Puzzles keep their own records per level
+    if *game_mode == GameMode::Puzzle {
+        summary_state.records = None;
+        return;
+    }
+
+    let previous = load_records(*game_mode);
+
+    // The computer playing counts as taking hints, so the hint settings decide about both
+    if !session.is_comparable() || hint_settings.disqualifies(session.stats.hints) {
+        summary_state.records = Some((previous, previous));
+        return;
+    }
+
+    let current = Records {
+        best_score: previous.best_score.max(score.score),
+        highest_tile: previous
+            .highest_tile
+            .max(grid.board().highest_score().unwrap_or(0)),
+    };
+    if let Err(err) = save_records(*game_mode, current) {
+        eprintln!("Could not save records: {}", err);
+    }
+
+    summary_state.records = Some((previous, current));
+}
+
+fn show_summary(
+    mut commands: Commands,
+    score: Res<ScoreState>,
+    grid: Res<Grid>,
+    session: Res<GameSession>,
+    mut summary_state: ResMut<SummaryState>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    daily_challenge: Res<DailyChallenge>,
+    game_events: Res<Events<RunningGameState>>,
+) {
+    let game_over = summary_state
+        .event_reader
+        .iter(&game_events)
+        .any(|event| *event == RunningGameState::GameOver);
+
+    if !game_over {
+        return;
+    }
+
+    let highest_tile = grid.board().highest_score().unwrap_or(0);
+    let records = summary_state.records;
+
+    spawn_board_fade(&mut commands, &mut materials);
+
+    let font_handle = asset_server
+        .load("assets/bungee_inline_regular.ttf")
+        .unwrap();
+    let seconds = session.stats.play_time as u32;
+    // Games that weren't saved keep the records they started with, so they show no new ones
+    let new_best_score = records.map_or(false, |(previous, current)| {
+        current.best_score > previous.best_score
+    });
+    let new_highest_tile = records.map_or(false, |(previous, current)| {
+        current.highest_tile > previous.highest_tile
+    });
+    let button_material = materials.add(Color::rgba(1., 1., 1., 0.6).into());
+    let text_color = theme.text();
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect::all(Val::Percent(10.)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with(GameOverScreen)
+        .with_children(|parent| {
+            spawn_line(
+                parent,
+                font_handle.clone(),
+                text_color,
+                LocalizedText::new("game-over"),
+                40.,
+            );
+            spawn_line(
+                parent,
+                font_handle.clone(),
+                text_color,
+                LocalizedText::new("summary-score").with_arg("score", score.score),
+                30.,
+            );
+            if let Some((_, current)) = records {
+                spawn_record_line(
+                    parent,
+                    font_handle.clone(),
+                    text_color,
+                    ("summary-best", "summary-best-new"),
+                    current.best_score,
+                    new_best_score,
+                );
+            }
+            spawn_record_line(
+                parent,
+                font_handle.clone(),
+                text_color,
+                ("summary-highest-square", "summary-highest-square-new"),
+                highest_tile,
+                new_highest_tile,
+            );
+            spawn_line(
+                parent,
+                font_handle.clone(),
+                text_color,
+                LocalizedText::new("summary-moves").with_arg("moves", session.stats.moves),
+                20.,
+            );
+            spawn_line(
+                parent,
+                font_handle.clone(),
+                text_color,
+                LocalizedText::new("summary-time")
+                    .with_arg("minutes", seconds / 60)
+                    .with_arg("seconds", format!("{:02}", seconds % 60)),
+                20.,
+            );
+            if let Some(result) = &daily_challenge.result {
+                for line in result.lines() {
+                    spawn_line(parent, font_handle.clone(), text_color, line, 16.);
+                }
+            }
+
+            spawn_button(
+                parent,
+                font_handle.clone(),
+                button_material.clone(),
+                "restart",
+            )
+            .with(RestartButton);
+            spawn_button(
+                parent,
+                font_handle.clone(),
+                button_material.clone(),
+                "view-replay",
+            )
+            .with(ReplayButton);
+            spawn_button(parent, font_handle.clone(), button_material, "main-menu")
+                .with(MenuButton);
+        });
+}
+
+/// Keeps the moves of every finished game, so that it can be watched or shared afterwards
+fn save_last_replay(
+    game_mode: Res<GameMode>,
+    session: Res<GameSession>,
+    mut summary_state: ResMut<SummaryState>,
+    game_events: Res<Events<RunningGameState>>,
+) {
+    let game_over = summary_state
+        .replay_reader
+        .iter(&game_events)
+        .any(|event| *event == RunningGameState::GameOver);
+
+    // Puzzles start from their level instead of the board of their mode
+    if !game_over || *game_mode == GameMode::Puzzle || !session.is_comparable() {
+        return;
+    }
+
+    let replay = ReplayFile {
+        mode: *game_mode,
+        seed: session.rng.seed,
+        moves: session.history.clone(),
+    };
+    let result = storage::data_file(LAST_REPLAY_FILE)
+        .and_then(|path| std::fs::write(path, replay.to_string()));
+    if let Err(err) = result {
+        eprintln!("Could not save the replay: {}", err);
+    }
+}
+
+/// Dims the board behind the summary, removed together with it on restart
+fn spawn_board_fade(commands: &mut Commands, materials: &mut Assets<ColorMaterial>) {
+    let (x, y) = calculate_grid_position(0, 0);
+    let grid_width = (GRID_SIZE * SQUARE_WIDTH + (GRID_SIZE - 1) * SQUARE_MARGIN) as f32;
+    let faded = Color::rgba(0.45, 0.45, 0.45, 0.7);
+    let transparent = Color::rgba(faded.r, faded.g, faded.b, 0.);
+
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(transparent.into()),
+            draw: Draw {
+                is_transparent: true,
+                ..Default::default()
+            },
+            sprite: Sprite {
+                size: Vec2::new(
+                    grid_width + SQUARE_MARGIN as f32,
+                    grid_width + SQUARE_MARGIN as f32,
+                ),
+                resize_mode: SpriteResizeMode::Manual,
+            },
+            translation: Translation::new(x + grid_width / 2., y + grid_width / 2., 10.),
+            ..Default::default()
+        })
+        .with(GameOverScreen)
+        .with(
+            Tween::<animation::MaterialColorLens>::new(transparent, faded, FADE_DURATION)
+                .with_ease(Easing::EaseOutQuad),
+        );
+}
+
+fn spawn_line(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    color: Color,
+    text: LocalizedText,
+    font_size: f32,
+) {
+    parent
+        .spawn(TextComponents {
+            style: Style {
+                margin: Rect::all(Val::Px(5.0)),
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font,
+                style: TextStyle { font_size, color },
+            },
+            ..Default::default()
+        })
+        .with(text)
+        .with(Themed::Text);
+}
+
+/// A line of the summary, followed by a badge if it is a new record
+fn spawn_record_line(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    color: Color,
+    (key, new_key): (&'static str, &'static str),
+    score: u64,
+    is_new: bool,
+) {
+    let key = if is_new { new_key } else { key };
+    let text = LocalizedText::new(key).with_arg("score", score);
+    spawn_line(parent, font, color, text, 20.);
+}
+
+fn spawn_button<'a, 'b>(
+    parent: &'b mut ChildBuilder<'a>,
+    font: Handle<Font>,
+    material: Handle<ColorMaterial>,
+    label: &'static str,
+) -> &'b mut ChildBuilder<'a> {
+    parent
+        .spawn(ButtonComponents {
+            style: Style {
+                margin: Rect {
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                padding: Rect::all(Val::Px(15.)),
+                ..Default::default()
+            },
+            material,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextComponents {
+                    text: Text {
+                        value: String::new(),
+                        font,
+                        style: TextStyle {
+                            font_size: 25.,
+                            color: Color::rgb(0., 0., 0.),
+                        },
+                    },
+                    ..Default::default()
+                })
+                .with(LocalizedText::new(label));
+        })
+}
+
+fn summary_button_system(
+    mut commands: Commands,
+    mut session: ResMut<GameSession>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    mut restart_events: ResMut<Events<RestartGame>>,
+    mut game_events: ResMut<Events<RunningGameState>>,
+    mut replay_query: Query<With<ReplayButton, &Interaction>>,
+    mut menu_query: Query<With<MenuButton, &Interaction>>,
+    mut screen_query: Query<With<GameOverScreen, Entity>>,
+) {
+    for interaction in &mut replay_query.iter() {
+        // Buttons stay clicked while held, so only queue the replay once
+        if let Interaction::Clicked = *interaction {
+            if session.queued_replay.is_none() && !session.history.is_empty() {
+                session.queue_replay();
+                restart_events.send(RestartGame);
+            }
+        }
+    }
+
+    for interaction in &mut menu_query.iter() {
+        if let Interaction::Clicked = *interaction {
+            for entity in &mut screen_query.iter() {
+                commands.despawn_recursive(entity);
+            }
+            game_events.send(RunningGameState::MainMenu);
+            menu::spawn_main_menu(&mut commands, &mut materials, &asset_server, *theme);
+        }
+    }
+}
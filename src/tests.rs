@@ -0,0 +1,467 @@
+//! Plays the game headlessly: key presses go in, time advances by fixed steps and the tests look
+//! at the resulting `Grid`, score, game state and square entities
+
+use super::*;
+use bevy::input::keyboard::{ElementState, KeyboardInput};
+use std::time::Duration;
+
+/// Every frame takes exactly this long, as if the game ran at a steady 60 fps
+const FRAME_TIME: f32 = 1. / 60.;
+const SEED: u64 = 42;
+
+struct FixedTimeStep(f32);
+
+/// Replaces the measured frame time, so that animations and replays progress deterministically
+fn fixed_time_step(step: Res<FixedTimeStep>, mut time: ResMut<Time>) {
+    time.delta = Duration::from_secs_f32(step.0);
+    time.delta_seconds = step.0;
+    time.delta_seconds_f64 = step.0 as f64;
+}
+
+/// The game as `main` runs it, minus the window, the renderer and the UI
+struct TestApp {
+    app: App,
+}
+
+impl TestApp {
+    /// Starts a game from the given position and plays its first frame
+    fn new(starting_position: StartingPosition) -> TestApp {
+        let mut builder = App::build();
+        // Bevy has no minimal plugin group yet, these are the plugins that work without a window
+        builder
+            .add_plugin(bevy::type_registry::TypeRegistryPlugin::default())
+            .add_plugin(bevy::core::CorePlugin::default())
+            .add_plugin(bevy::input::InputPlugin::default())
+            .add_plugin(bevy::asset::AssetPlugin::default())
+            .add_plugin(bevy::text::TextPlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<ColorMaterial>()
+            .add_resource(FixedTimeStep(FRAME_TIME))
+            // Added after the core plugin, so it runs after the time has been measured
+            .add_system_to_stage(bevy::app::stage::FIRST, fixed_time_step.system())
+            .add_resource(GameState {
+                state: RunningGameState::Running,
+                event_reader: Default::default(),
+                restart_reader: Default::default(),
+                move_reader: Default::default(),
+            })
+            .add_resource(GameSession::new(
+                GameRng::new(Some(SEED)),
+                &starting_position,
+            ))
+            .add_resource(starting_position)
+            .add_resource(Grid::new())
+            .add_plugin(GameplayPlugin);
+
+        let mut app = builder.app;
+        let square_colors = {
+            let mut materials = app.resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+            SquareColors::new(&mut materials, Palette::default())
+        };
+        app.resources.insert(square_colors);
+
+        let mut test_app = TestApp { app };
+        test_app.restart();
+        test_app
+    }
+
+    fn update(&mut self) {
+        self.app.update();
+    }
+
+    /// Plays frames until at least `seconds` have passed
+    fn advance(&mut self, seconds: f32) {
+        let frames = (seconds / FRAME_TIME).ceil() as u32;
+        for _ in 0..frames {
+            self.update();
+        }
+    }
+
+    /// Presses and releases the key within a single frame
+    fn press(&mut self, key_code: KeyCode) {
+        {
+            let mut events = self
+                .app
+                .resources
+                .get_mut::<Events<KeyboardInput>>()
+                .unwrap();
+            for &state in &[ElementState::Pressed, ElementState::Released] {
+                events.send(KeyboardInput {
+                    scan_code: 0,
+                    key_code: Some(key_code),
+                    state,
+                });
+            }
+        }
+        self.update();
+    }
+
+    fn restart(&mut self) {
+        self.app
+            .resources
+            .get_mut::<Events<RestartGame>>()
+            .unwrap()
+            .send(RestartGame);
+        self.update();
+    }
+
+    fn board(&self) -> Board {
+        self.app.resources.get::<Grid>().unwrap().board()
+    }
+
+    fn score(&self) -> u64 {
+        self.app.resources.get::<ScoreState>().unwrap().score
+    }
+
+    /// The score the score text counts up to
+    fn shown_score(&self) -> u64 {
+        self.app.resources.get::<ScoreState>().unwrap().shown
+    }
+
+    fn moves(&self) -> u32 {
+        self.app.resources.get::<GameSession>().unwrap().stats.moves
+    }
+
+    fn state(&self) -> RunningGameState {
+        self.app.resources.get::<GameState>().unwrap().state
+    }
+
+    /// Grid positions of all squares still in play, sorted
+    fn squares(&self) -> Vec<(u32, u32)> {
+        let mut positions: Vec<(u32, u32)> = self
+            .app
+            .world
+            .query::<Without<Dying, With<GameSquare, &GridPosition>>>()
+            .iter()
+            .map(|position| (position.0, position.1))
+            .collect();
+        positions.sort();
+        positions
+    }
+
+    /// All square entities, including those which only finish their animation
+    fn square_count(&self) -> usize {
+        self.app
+            .world
+            .query::<With<GameSquare, Entity>>()
+            .iter()
+            .count()
+    }
+
+    /// What the consistency check found in the last frame
+    #[cfg(debug_assertions)]
+    fn problems(&self) -> Vec<String> {
+        self.app
+            .resources
+            .get::<consistency::GridConsistency>()
+            .unwrap()
+            .problems
+            .clone()
+    }
+}
+
+fn board(squares: &[((u32, u32), u64)]) -> Board {
+    let mut board = Board::new(GRID_SIZE);
+    for &(position, score) in squares {
+        board.set(position, Some(score));
+    }
+    board
+}
+
+/// Starts from the given board without ever spawning squares
+fn fixed_position(board: Board) -> StartingPosition {
+    StartingPosition {
+        board,
+        spawn_policy: SpawnPolicy::Disabled,
+        combo_rules: None,
+    }
+}
+
+#[test]
+fn restart_spawns_the_starting_position() {
+    let expected = StartingPosition::classic().board;
+    let app = TestApp::new(StartingPosition::classic());
+
+    assert_eq!(app.board(), expected);
+    assert_eq!(app.squares(), vec![(1, 2), (2, 1)]);
+    assert_eq!(app.square_count(), 2);
+    assert_eq!(app.score(), 0);
+    assert_eq!(app.state(), RunningGameState::Running);
+}
+
+#[test]
+fn merging_squares_scores_their_points() {
+    let mut app = TestApp::new(fixed_position(board(&[((0, 0), 1), ((1, 0), 1)])));
+
+    app.press(KeyCode::Left);
+    app.update();
+
+    assert_eq!(app.board(), board(&[((0, 0), 2)]));
+    assert_eq!(app.squares(), vec![(0, 0)]);
+    assert_eq!(app.score(), 2);
+
+    // The square merged into stays around until it has flown to the score, which then counts up
+    assert_eq!(app.square_count(), 2);
+    assert_eq!(app.shown_score(), 0);
+    app.advance(1.);
+    assert_eq!(app.square_count(), 1);
+    assert_eq!(app.shown_score(), 2);
+}
+
+#[test]
+fn merged_squares_leave_once_their_merge_sequence_finishes() {
+    let mut app = TestApp::new(fixed_position(board(&[((0, 0), 1), ((1, 0), 1)])));
+    let mut finished_reader = app
+        .app
+        .resources
+        .get::<Events<animation::AnimationFinished>>()
+        .unwrap()
+        .get_reader_current();
+
+    app.press(KeyCode::Left);
+    let mut finished = vec![];
+    for _ in 0..60 {
+        app.update();
+        let events = app
+            .app
+            .resources
+            .get::<Events<animation::AnimationFinished>>()
+            .unwrap();
+        finished.extend(
+            finished_reader
+                .iter(&events)
+                .filter(|event| event.kind == animation::AnimationKind::Sequence)
+                .map(|event| event.entity),
+        );
+    }
+
+    // Only the square merged into plays a merge sequence, and it is gone once that finishes
+    assert_eq!(finished.len(), 1);
+    assert!(app.app.world.get::<GameSquare>(finished[0]).is_err());
+    assert_eq!(app.square_count(), 1);
+}
+
+#[test]
+fn squares_move_by_one_cell() {
+    let mut app = TestApp::new(fixed_position(board(&[((3, 3), 1)])));
+
+    app.press(KeyCode::Down);
+
+    assert_eq!(app.board(), board(&[((3, 2), 1)]));
+    assert_eq!(app.squares(), vec![(3, 2)]);
+}
+
+#[test]
+fn squares_jump_to_their_cell_with_reduced_motion() {
+    let mut app = TestApp::new(fixed_position(board(&[((3, 3), 1)])));
+    app.app
+        .resources
+        .get_mut::<animation::AnimationSettings>()
+        .unwrap()
+        .reduced_motion = true;
+
+    app.press(KeyCode::Down);
+    // The slide is queued with the move and starts a frame later, so it ends the frame after that
+    app.update();
+    app.update();
+
+    let (x, y) = calculate_grid_position(3, 2);
+    let translations: Vec<Vec3> = app
+        .app
+        .world
+        .query::<With<GameSquare, &Translation>>()
+        .iter()
+        .map(|translation| translation.0)
+        .collect();
+    assert_eq!(translations, vec![Vec3::new(x, y, 1.)]);
+}
+
+#[test]
+fn merged_squares_fade_out_with_reduced_motion() {
+    let mut app = TestApp::new(fixed_position(board(&[((0, 0), 1), ((1, 0), 1)])));
+    app.app
+        .resources
+        .get_mut::<animation::AnimationSettings>()
+        .unwrap()
+        .reduced_motion = true;
+
+    app.press(KeyCode::Left);
+    app.update();
+
+    // Both squares fade, the one merged into out and the other one in at its new cell
+    let fading = app
+        .app
+        .world
+        .query::<&animation::Tween<animation::MaterialColorLens>>()
+        .iter()
+        .count();
+    assert!(fading > 0);
+    assert_eq!(app.square_count(), 2);
+
+    app.advance(FADE_DURATION + 0.1);
+    assert_eq!(app.square_count(), 1);
+    assert_eq!(app.shown_score(), 2);
+}
+
+#[test]
+fn fades_remove_their_materials_once_done() {
+    let mut app = TestApp::new(StartingPosition::classic());
+    let material_count = |app: &TestApp| {
+        app.app
+            .resources
+            .get::<Assets<ColorMaterial>>()
+            .unwrap()
+            .iter()
+            .count()
+    };
+    let before = material_count(&app);
+    app.app
+        .resources
+        .get_mut::<animation::AnimationSettings>()
+        .unwrap()
+        .reduced_motion = true;
+
+    // The new squares of the restart fade in a frame later, each part with a material of its own
+    app.restart();
+    app.update();
+    assert!(material_count(&app) > before);
+
+    app.advance(1.);
+    assert_eq!(material_count(&app), before);
+}
+
+#[test]
+fn a_stuck_board_ends_the_game() {
+    let mut checkerboard = Board::new(GRID_SIZE);
+    for x in 0..GRID_SIZE {
+        for y in 0..GRID_SIZE {
+            checkerboard.set((x, y), Some(if (x + y) % 2 == 0 { 1 } else { 2 }));
+        }
+    }
+    let mut app = TestApp::new(fixed_position(checkerboard.clone()));
+
+    app.press(KeyCode::Up);
+    app.update();
+
+    assert_eq!(app.state(), RunningGameState::GameOver);
+
+    // Moves are ignored once the game is over
+    app.press(KeyCode::Up);
+    assert_eq!(app.board(), checkerboard);
+}
+
+#[test]
+fn restart_resets_board_and_score() {
+    let start = board(&[((0, 0), 1), ((1, 0), 1)]);
+    let mut app = TestApp::new(fixed_position(start.clone()));
+
+    app.press(KeyCode::Left);
+    app.update();
+    assert_eq!(app.score(), 2);
+
+    app.restart();
+    app.advance(1.);
+
+    assert_eq!(app.board(), start);
+    assert_eq!(app.squares(), vec![(0, 0), (1, 0)]);
+    assert_eq!(app.square_count(), 2);
+    assert_eq!(app.score(), 0);
+}
+
+#[test]
+fn replays_end_on_the_same_board() {
+    let mut app = TestApp::new(StartingPosition::classic());
+
+    for &key_code in &[KeyCode::Left, KeyCode::Up, KeyCode::Right, KeyCode::Down] {
+        app.press(key_code);
+        app.advance(0.5);
+    }
+    let played = app.board();
+    let score = app.score();
+
+    app.app
+        .resources
+        .get_mut::<GameSession>()
+        .unwrap()
+        .queue_replay();
+    app.restart();
+    app.advance(4. * replay::REPLAY_MOVE_INTERVAL + 1.);
+
+    assert_eq!(app.board(), played);
+    assert_eq!(app.score(), score);
+}
+
+#[test]
+fn loaded_positions_keep_their_score_and_moves() {
+    let position: Position = "4x4 .,.,.,./.,4,.,./.,.,2,2/.,.,.,. score:12 moves:5 seed:7"
+        .parse()
+        .unwrap();
+    let mut app = TestApp::new(StartingPosition::classic());
+
+    app.app
+        .resources
+        .get_mut::<Events<LoadPosition>>()
+        .unwrap()
+        .send(LoadPosition(position.clone()));
+    app.update();
+    app.update();
+
+    assert_eq!(app.board(), position.board);
+    assert_eq!(app.squares(), vec![(1, 2), (2, 1), (3, 1)]);
+    assert_eq!(app.score(), 12);
+    assert_eq!(app.moves(), 5);
+    assert_eq!(
+        app.app
+            .resources
+            .get::<Grid>()
+            .unwrap()
+            .to_notation(app.score(), app.moves(), 7),
+        position.to_string()
+    );
+
+    // Neither this game nor the ones restarted from the same board set records
+    app.restart();
+    assert!(!app
+        .app
+        .resources
+        .get::<GameSession>()
+        .unwrap()
+        .is_comparable());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn grid_and_squares_stay_consistent() {
+    let mut app = TestApp::new(StartingPosition::arcade());
+
+    for &key_code in [KeyCode::Left, KeyCode::Up, KeyCode::Right, KeyCode::Down]
+        .iter()
+        .cycle()
+        .take(40)
+    {
+        app.press(key_code);
+        app.advance(0.1);
+        assert_eq!(app.problems(), Vec::<String>::new());
+    }
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn squares_missing_from_the_grid_are_reported() {
+    let mut app = TestApp::new(fixed_position(board(&[((0, 0), 1)])));
+    let entity = app
+        .app
+        .resources
+        .get_mut::<Grid>()
+        .unwrap()
+        .take_at((0, 0))
+        .unwrap()
+        .entity;
+
+    app.update();
+
+    assert_eq!(
+        app.problems(),
+        vec![format!("{:?} at (0, 0) is missing from the grid", entity)]
+    );
+}
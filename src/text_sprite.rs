@@ -1,3 +1,4 @@
+use crate::animation::{self, AnimationKind, ComponentLens, Lens};
 use bevy::prelude::*;
 use bevy::render::{
     draw::{DrawContext, Drawable},
@@ -5,6 +6,19 @@ use bevy::render::{
 };
 use bevy::text::{DrawableText, FontAtlasSet};
 
+/// Draws text in the world, like a sprite, instead of in the UI
+#[derive(Default)]
+pub struct TextSpritePlugin;
+
+impl Plugin for TextSpritePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(bevy::app::stage::POST_UPDATE, update_text_sprites.system())
+            .add_system_to_stage(bevy::render::stage::DRAW, draw_text_sprites.system());
+
+        animation::add_tween_systems::<TextSpriteColorLens>(app);
+    }
+}
+
 #[derive(Default)]
 pub struct TextSprite {
     pub text: String,
@@ -12,17 +26,28 @@ pub struct TextSprite {
     pub font: Handle<Font>,
 }
 
+/// The size the text takes up, known once its font is loaded
 #[derive(Default)]
-pub struct TextSpriteSize(Vec2);
+pub struct TextSpriteSize(Option<Vec2>);
 
 pub fn update_text_sprites(
     mut textures: ResMut<Assets<Texture>>,
     fonts: Res<Assets<Font>>,
     mut font_atlas_sets: ResMut<Assets<FontAtlasSet>>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut query: Query<(Changed<TextSprite>, &mut TextSpriteSize)>,
+    mut changed_query: Query<(Changed<TextSprite>, &mut TextSpriteSize)>,
+    mut query: Query<(&TextSprite, &mut TextSpriteSize)>,
 ) {
+    for (_, mut sprite_size) in &mut changed_query.iter() {
+        sprite_size.0 = None;
+    }
+
+    // Sprites spawned in the first frames wait for their font
     for (text_sprite, mut sprite_size) in &mut query.iter() {
+        if sprite_size.0.is_some() || fonts.get(&text_sprite.font).is_none() {
+            continue;
+        }
+
         let font_atlases = font_atlas_sets
             .get_or_insert_with(Handle::from_id(text_sprite.font.id), || {
                 FontAtlasSet::new(text_sprite.font)
@@ -35,7 +60,7 @@ pub fn update_text_sprites(
             &text_sprite.text,
         );
 
-        sprite_size.0 = Vec2::new(width, text_sprite.text_style.font_size);
+        sprite_size.0 = Some(Vec2::new(width, text_sprite.text_style.font_size));
     }
 }
 
@@ -47,30 +72,66 @@ pub fn draw_text_sprites(
     texture_atlases: Res<Assets<TextureAtlas>>,
     mut render_resource_bindings: ResMut<RenderResourceBindings>,
     mut asset_render_resource_bindings: ResMut<AssetRenderResourceBindings>,
-    mut query: Query<(&mut Draw, &TextSprite, &TextSpriteSize, &Translation)>,
+    mut query: Query<(
+        &mut Draw,
+        &TextSprite,
+        &TextSpriteSize,
+        &Translation,
+        Option<&Transform>,
+    )>,
 ) {
-    for (mut draw, text_sprite, text_sprite_size, translate) in &mut query.iter() {
-        let position = translate.0 - (text_sprite_size.0 / 2.).extend(0.);
+    for (mut draw, text_sprite, text_sprite_size, translate, transform) in &mut query.iter() {
+        let font_atlas_set = font_atlas_sets.get(&text_sprite.font.as_handle::<FontAtlasSet>());
+        let (size, font, font_atlas_set) = match (
+            text_sprite_size.0,
+            fonts.get(&text_sprite.font),
+            font_atlas_set,
+        ) {
+            (Some(size), Some(font), Some(font_atlas_set)) => (size, font, font_atlas_set),
+            _ => continue,
+        };
 
-        println!(
-            "Pos: {}, size: {}, text: {}",
-            position, text_sprite_size.0, text_sprite.text
-        );
+        // Text sprites can be children, like the labels of squares, which follow their parent
+        let (center, scale) = match transform {
+            Some(transform) => (
+                transform.value.w_axis().truncate(),
+                transform.value.x_axis().truncate().length(),
+            ),
+            None => (translate.0, 1.),
+        };
+        let position = center - (size / 2.).extend(0.);
+        // Glyphs are only rendered at their font size, so shrinking text fades out instead
+        let mut style = text_sprite.text_style.clone();
+        style.color.a *= scale.min(1.);
 
         let mut drawable_text = DrawableText {
-            font: fonts.get(&text_sprite.font).unwrap(),
-            font_atlas_set: font_atlas_sets
-                .get(&text_sprite.font.as_handle::<FontAtlasSet>())
-                .unwrap(),
+            font,
+            font_atlas_set,
             texture_atlases: &texture_atlases,
             render_resource_bindings: &mut render_resource_bindings,
             asset_render_resource_bindings: &mut asset_render_resource_bindings,
             position,
             msaa: &msaa,
-            style: &text_sprite.text_style,
+            style: &style,
             text: &text_sprite.text,
-            container_size: text_sprite_size.0,
+            container_size: size,
         };
         drawable_text.draw(&mut draw, &mut draw_context).unwrap();
     }
 }
+
+pub struct TextSpriteColorLens;
+
+impl Lens for TextSpriteColorLens {
+    type Value = Color;
+
+    const KIND: AnimationKind = AnimationKind::TextSpriteColor;
+}
+
+impl ComponentLens for TextSpriteColorLens {
+    type Target = TextSprite;
+
+    fn set(target: &mut TextSprite, value: Color) {
+        target.text_style.color = value;
+    }
+}
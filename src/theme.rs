@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_squares::render::{self, Palette};
+use image::Rgba;
+use std::fmt;
+use std::str::FromStr;
+
+/// The colors of everything around the squares, chosen with `--theme`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Black and white with a bright accent, for players who need more contrast
+    HighContrast,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::Light
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Theme, String> {
+        match s {
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            "high-contrast" => Ok(Theme::HighContrast),
+            _ => Err(format!(
+                "unknown theme `{}`, expected light, dark or high-contrast",
+                s
+            )),
+        }
+    }
+}
+
+/// Written as accepted by `--theme`
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high-contrast",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Theme {
+    /// Behind the board and all screens
+    pub fn background(self) -> Color {
+        match self {
+            Theme::Light => Color::rgb(255. / 255., 211. / 255., 182. / 255.),
+            Theme::Dark => Color::rgb(40. / 255., 32. / 255., 36. / 255.),
+            Theme::HighContrast => Color::rgb(0., 0., 0.),
+        }
+    }
+
+    pub fn text(self) -> Color {
+        match self {
+            Theme::Light => Color::rgb(76. / 255., 42. / 255., 44. / 255.),
+            Theme::Dark => Color::rgb(240. / 255., 222. / 255., 210. / 255.),
+            Theme::HighContrast => Color::rgb(1., 1., 1.),
+        }
+    }
+
+    /// Labels and highlights that should stand out less than the main text
+    pub fn accent(self) -> Color {
+        match self {
+            Theme::Light => Color::rgb(204. / 255., 112. / 255., 119. / 255.),
+            Theme::Dark => Color::rgb(230. / 255., 140. / 255., 150. / 255.),
+            Theme::HighContrast => Color::rgb(1., 220. / 255., 0.),
+        }
+    }
+
+    /// The empty cells of the board
+    pub fn cell(self) -> Color {
+        match self {
+            Theme::Light => Color::rgba(0., 0., 0., 0.2),
+            Theme::Dark => Color::rgba(1., 1., 1., 0.1),
+            Theme::HighContrast => Color::rgba(1., 1., 1., 0.3),
+        }
+    }
+
+    /// The same colors for images drawn by `bevy_squares::render`
+    pub fn render_style(self, palette: Palette) -> render::Style {
+        render::Style {
+            background: to_rgba(self.background()),
+            text: to_rgba(self.text()),
+            cell: to_rgba(self.cell()),
+            palette,
+        }
+    }
+}
+
+/// The part of the theme a material or text is drawn in, so that it follows when the theme changes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Themed {
+    Background,
+    Text,
+    Accent,
+    Cell,
+}
+
+impl Themed {
+    pub fn color(self, theme: Theme) -> Color {
+        match self {
+            Themed::Background => theme.background(),
+            Themed::Text => theme.text(),
+            Themed::Accent => theme.accent(),
+            Themed::Cell => theme.cell(),
+        }
+    }
+}
+
+pub fn to_color(color: Rgba<u8>) -> Color {
+    Color::rgba(
+        color[0] as f32 / 255.,
+        color[1] as f32 / 255.,
+        color[2] as f32 / 255.,
+        color[3] as f32 / 255.,
+    )
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    let channel = |value: f32| (value * 255.).round() as u8;
+    Rgba([
+        channel(color.r),
+        channel(color.g),
+        channel(color.b),
+        channel(color.a),
+    ])
+}